@@ -0,0 +1,367 @@
+use crate::lexer::{self, Span, Token};
+use crate::stopwords;
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Languages with a Snowball stemmer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Arabic,
+    Danish,
+    Dutch,
+    #[default]
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl Language {
+    fn algorithm(&self) -> Algorithm {
+        match self {
+            Language::Arabic => Algorithm::Arabic,
+            Language::Danish => Algorithm::Danish,
+            Language::Dutch => Algorithm::Dutch,
+            Language::English => Algorithm::English,
+            Language::Finnish => Algorithm::Finnish,
+            Language::French => Algorithm::French,
+            Language::German => Algorithm::German,
+            Language::Greek => Algorithm::Greek,
+            Language::Hungarian => Algorithm::Hungarian,
+            Language::Italian => Algorithm::Italian,
+            Language::Norwegian => Algorithm::Norwegian,
+            Language::Portuguese => Algorithm::Portuguese,
+            Language::Romanian => Algorithm::Romanian,
+            Language::Russian => Algorithm::Russian,
+            Language::Spanish => Algorithm::Spanish,
+            Language::Swedish => Algorithm::Swedish,
+            Language::Tamil => Algorithm::Tamil,
+            Language::Turkish => Algorithm::Turkish,
+        }
+    }
+}
+
+/// What happens to tokens made only of punctuation and symbols.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Punctuation {
+    /// Not indexed and not counted as a position.
+    #[default]
+    Drop,
+    /// Indexed like words.
+    Keep,
+}
+
+/// How a text is split into tokens, chosen per parser.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tokenizer {
+    /// Words, numbers and single symbols.
+    #[default]
+    Prose,
+    /// Identifiers and `::`/`.` paths are kept whole and also split into their
+    /// camelCase and snake_case words.
+    Code,
+}
+
+/// How tokens are normalized after the lexer.
+///
+/// Recorded in the index, queries must be analyzed exactly like the documents were.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalysisSettings {
+    pub language: Language,
+    /// Fold accents away after case folding, so "café" matches "cafe".
+    pub fold_accents: bool,
+    /// Reduce words to their stem, so "indexing", "indexed" and "indexes" all become "index".
+    pub stemming: bool,
+    /// Drop the built-in stop words of the language ("the", "and", "of"...) from prose.
+    /// Code files keep them, `for`, `if` or a function named `all` are not noise there.
+    pub stop_words: bool,
+    /// Additional words to drop, on top of the built-in list.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra_stop_words: Vec<String>,
+    pub punctuation: Punctuation,
+}
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        Self {
+            language: Language::default(),
+            fold_accents: false,
+            stemming: false,
+            stop_words: true,
+            extra_stop_words: Vec::new(),
+            punctuation: Punctuation::default(),
+        }
+    }
+}
+
+/// A term produced by the analyzer with its position in the token stream.
+///
+/// Removed stop words still take up a position so phrases keep their gaps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub position: u32,
+    pub text: String,
+    /// Where the token of the term is in the analyzed text.
+    pub span: Span,
+}
+
+/// One step of the analysis pipeline, returning `None` drops the token.
+pub trait TokenFilter: Send + Sync {
+    fn filter(&self, token: String) -> Option<String>;
+}
+
+/// Full Unicode case folding ("ÄRGER" and "Ärger" become "ärger", "Straße" becomes "strasse"),
+/// optionally followed by accent folding ("ärger" becomes "arger").
+///
+/// This is the only place text is lowercased, parsers and the lexer leave the case alone.
+pub struct FoldFilter {
+    fold_accents: bool,
+}
+
+impl FoldFilter {
+    pub fn new(fold_accents: bool) -> Self {
+        Self { fold_accents }
+    }
+
+    pub fn fold(&self, token: &str) -> String {
+        let folded = caseless::default_case_fold_str(token);
+        if !self.fold_accents {
+            return folded;
+        }
+        // Decompose and drop the combining marks, then recompose what is left.
+        folded
+            .nfd()
+            .filter(|&c| !is_combining_mark(c))
+            .nfc()
+            .collect()
+    }
+}
+
+impl TokenFilter for FoldFilter {
+    fn filter(&self, token: String) -> Option<String> {
+        Some(self.fold(&token))
+    }
+}
+
+/// Removes stop words.
+pub struct StopFilter {
+    words: HashSet<String>,
+}
+
+impl StopFilter {
+    /// The words are folded like the tokens they are compared with.
+    pub fn new(settings: &AnalysisSettings, fold: &FoldFilter) -> Self {
+        let builtin = if settings.stop_words {
+            stopwords::for_language(settings.language)
+        } else {
+            &[]
+        };
+        let words = builtin
+            .iter()
+            .copied()
+            .chain(settings.extra_stop_words.iter().map(String::as_str))
+            .map(|word| fold.fold(word))
+            .collect();
+        Self { words }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+impl TokenFilter for StopFilter {
+    fn filter(&self, token: String) -> Option<String> {
+        (!self.words.contains(&token)).then_some(token)
+    }
+}
+
+/// Snowball stemming.
+pub struct StemFilter {
+    stemmer: Stemmer,
+}
+
+impl StemFilter {
+    pub fn new(language: Language) -> Self {
+        Self {
+            stemmer: Stemmer::create(language.algorithm()),
+        }
+    }
+}
+
+impl TokenFilter for StemFilter {
+    fn filter(&self, token: String) -> Option<String> {
+        // Numbers and symbols are left alone.
+        if !token.chars().any(char::is_alphabetic) {
+            return Some(token);
+        }
+        Some(self.stemmer.stem(&token).into_owned())
+    }
+}
+
+/// The lexer followed by a pipeline of token filters.
+pub struct Analyzer {
+    punctuation: Punctuation,
+    filters: Vec<Box<dyn TokenFilter>>,
+    /// The same pipeline without the stop filter.
+    keep_stop_words: Vec<Box<dyn TokenFilter>>,
+}
+
+impl Analyzer {
+    pub fn new(settings: &AnalysisSettings) -> Self {
+        let pipeline = |stop_words: bool| {
+            let mut filters: Vec<Box<dyn TokenFilter>> = Vec::new();
+            let fold_filter = FoldFilter::new(settings.fold_accents);
+            // Stop lists hold whole words, so they run before stemming.
+            let stop_filter = StopFilter::new(settings, &fold_filter);
+            filters.push(Box::new(fold_filter));
+            if stop_words && !stop_filter.is_empty() {
+                filters.push(Box::new(stop_filter));
+            }
+            if settings.stemming {
+                filters.push(Box::new(StemFilter::new(settings.language)));
+            }
+            filters
+        };
+        Self {
+            punctuation: settings.punctuation,
+            filters: pipeline(true),
+            keep_stop_words: pipeline(false),
+        }
+    }
+
+    /// Runs a single token through the pipeline, with or without the stop filter.
+    pub fn analyze(&self, token: String, drop_stop_words: bool) -> Option<String> {
+        let filters = if drop_stop_words { &self.filters } else { &self.keep_stop_words };
+        filters.iter().try_fold(token, |token, filter| filter.filter(token))
+    }
+
+    /// Tokenizes the text of a document and analyzes every token. Stop words are
+    /// dropped from prose only.
+    pub fn terms(&self, text: &str, tokenizer: Tokenizer) -> Vec<Term> {
+        self.terms_with(text, tokenizer, tokenizer == Tokenizer::Prose)
+    }
+
+    /// Tokenizes a text and analyzes every token.
+    ///
+    /// The code tokenizer emits an identifier together with its sub-words, these
+    /// overlap: `HashMap` is at the same position as `Hash`, and `Map` follows.
+    pub fn terms_with(&self, text: &str, tokenizer: Tokenizer, drop_stop_words: bool) -> Vec<Term> {
+        let groups: Box<dyn Iterator<Item = Vec<(Token, u32)>>> = match tokenizer {
+            Tokenizer::Prose => Box::new(lexer::Lexer::new(text).map(|token| vec![(token, 0)])),
+            Tokenizer::Code => Box::new(lexer::CodeLexer::new(text)),
+        };
+        let mut terms = Vec::new();
+        let mut position = 0;
+        for group in groups {
+            let is_punctuation = group
+                .iter()
+                .all(|(token, _)| !token.text.chars().any(char::is_alphanumeric));
+            if self.punctuation == Punctuation::Drop && is_punctuation {
+                continue;
+            }
+            let mut width = 1;
+            for (token, offset) in group {
+                width = width.max(offset + 1);
+                if let Some(text) = self.analyze(token.text.to_string(), drop_stop_words) {
+                    terms.push(Term {
+                        position: position + offset,
+                        text,
+                        span: token.span,
+                    });
+                }
+            }
+            position += width;
+        }
+        terms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(terms: &[Term]) -> Vec<(&str, u32)> {
+        terms.iter().map(|term| (term.text.as_str(), term.position)).collect()
+    }
+
+    #[test]
+    fn stems_words_when_enabled() {
+        let analyzer = Analyzer::new(&AnalysisSettings::default());
+        let terms = analyzer.terms("indexing indexed", Tokenizer::Prose);
+        assert_eq!(positions(&terms), [("indexing", 0), ("indexed", 1)]);
+
+        let settings = AnalysisSettings {
+            stemming: true,
+            ..AnalysisSettings::default()
+        };
+        let terms = Analyzer::new(&settings).terms("indexing indexed", Tokenizer::Prose);
+        assert_eq!(positions(&terms), [("index", 0), ("index", 1)]);
+    }
+
+    #[test]
+    fn removed_stop_words_keep_their_positions() {
+        let analyzer = Analyzer::new(&AnalysisSettings::default());
+        let terms = analyzer.terms("The War of the Worlds", Tokenizer::Prose);
+        assert_eq!(positions(&terms), [("war", 1), ("worlds", 4)]);
+        assert_eq!((terms[1].span.start, terms[1].span.col), (15, 16));
+    }
+
+    #[test]
+    fn dropped_punctuation_takes_no_position() {
+        let analyzer = Analyzer::new(&AnalysisSettings::default());
+        let terms = analyzer.terms("fish, chips & peas", Tokenizer::Prose);
+        assert_eq!(positions(&terms), [("fish", 0), ("chips", 1), ("peas", 2)]);
+    }
+
+    #[test]
+    fn code_keeps_stop_words_and_overlaps_sub_words() {
+        let analyzer = Analyzer::new(&AnalysisSettings::default());
+        let terms = analyzer.terms("for item in self.allItems", Tokenizer::Code);
+        assert_eq!(
+            positions(&terms),
+            [
+                ("for", 0),
+                ("item", 1),
+                ("in", 2),
+                ("self.allitems", 3),
+                ("self", 3),
+                ("allitems", 4),
+                ("all", 4),
+                ("items", 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn folds_case_and_optionally_accents() {
+        let analyzer = Analyzer::new(&AnalysisSettings::default());
+        let terms = analyzer.terms("Café STRASSE Straße", Tokenizer::Prose);
+        assert_eq!(positions(&terms), [("café", 0), ("strasse", 1), ("strasse", 2)]);
+
+        let settings = AnalysisSettings {
+            fold_accents: true,
+            ..AnalysisSettings::default()
+        };
+        let terms = Analyzer::new(&settings).terms("Café Noël", Tokenizer::Prose);
+        assert_eq!(positions(&terms), [("cafe", 0), ("noel", 1)]);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::analysis::AnalysisSettings;
+use crate::error::Error;
+use crate::parsers::Field;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// What to do with files whose type could not be determined but that look like text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownFiles {
+    /// Index them as plain text.
+    #[default]
+    Text,
+    /// Skip them.
+    Skip,
+}
+
+/// Settings that belong to one indexed directory (a "collection").
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollectionConfig {
+    /// Per-field boosts applied to every query unless the query overrides them (e.g. `title^3`).
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub boosts: HashMap<Field, f64>,
+
+    /// Token analysis (language, stemming), recorded in the index when it is built.
+    pub analysis: AnalysisSettings,
+
+    /// Fallback for textual files of unknown type.
+    pub unknown_files: UnknownFiles,
+
+    /// External commands whose stdout is indexed, keyed by extension, e.g. `"djvu": "djvutxt {path} -"`.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub extractors: HashMap<String, String>,
+
+    /// Extra extensions indexed as code, mapped to a language name, e.g. `"inc": "php"`.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub languages: HashMap<String, String>,
+
+    /// Seconds an external extractor may run before it is killed, defaults to 30.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extractor_timeout_secs: Option<u64>,
+}
+
+impl CollectionConfig {
+    pub fn extractor_timeout(&self) -> Duration {
+        Duration::from_secs(self.extractor_timeout_secs.unwrap_or(30))
+    }
+}
+
+/// The contents of `config.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The directory that gets indexed, saved by `--index-path`.
+    pub index_path: Option<String>,
+
+    /// Collection settings keyed by their index path.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub collections: HashMap<String, CollectionConfig>,
+}
+
+impl Config {
+    /// Loads the configuration file, an absent file yields the default configuration.
+    pub fn load() -> Result<Self, Error> {
+        let config_path = get_config_path();
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+        let file = fs::File::open(&config_path)?;
+        let reader = std::io::BufReader::new(file);
+        serde_json::from_reader(reader)
+            .map_err(|e| Error::Config(format!("could not read the configuration {}: {e}", config_path.display())))
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let config_path = get_config_path();
+        // Create parent directories if needed
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::File::create(&config_path)?;
+        serde_json::to_writer_pretty(file, self).map_err(io::Error::from)?;
+        Ok(())
+    }
+
+    /// Returns the settings of the collection at the current index path.
+    pub fn collection(&self) -> CollectionConfig {
+        self.index_path
+            .as_deref()
+            .map(|path| self.collection_at(path))
+            .unwrap_or_default()
+    }
+
+    /// Returns the settings of the collection at a directory.
+    pub fn collection_at(&self, path: &str) -> CollectionConfig {
+        self.collections.get(path).cloned().unwrap_or_default()
+    }
+}
+
+/// Returns the configuration path based on the system used.
+/// If no config path found, it results to directory based config storage.
+pub fn get_config_path() -> PathBuf {
+    match dirs::config_dir() {
+        Some(path) => path.join("seroost").join("config.json"),
+        None => PathBuf::from("./indeces/config.json"),
+    }
+}
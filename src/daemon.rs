@@ -0,0 +1,181 @@
+use seroost::config::CollectionConfig;
+use seroost::index::{self, SearchHit};
+use seroost::Error;
+use seroost::searcher::{Filter, Searcher};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How long the CLI waits for the daemon before loading the index itself.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The socket of the daemon, next to the index file.
+pub fn socket_path() -> PathBuf {
+    index::get_indeces_path().with_file_name("seroost.sock")
+}
+
+/// A search, one JSON object per line on the socket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    /// Look for definitions of every word, like `--symbol`.
+    #[serde(default)]
+    pub symbol: bool,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+/// The ranked page of results, with the line matches of each hit filled in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResponse {
+    /// Number of matching documents, the results are `limit` of them from `offset`.
+    pub total: usize,
+    pub results: Vec<SearchHit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// The answer to a request, `{"results": {...}}` or `{"error": "..."}`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reply {
+    Results(SearchResponse),
+    Error(String),
+}
+
+impl SearchRequest {
+    /// Answers the request against a loaded index, the same way for the daemon and the CLI.
+    pub fn run(&self, searcher: &Searcher) -> Result<SearchResponse, Error> {
+        let results = searcher.search_text(&self.query, self.symbol, &Filter::default(), self.limit, self.offset)?;
+        Ok(SearchResponse {
+            total: results.total,
+            results: results.hits,
+            warning: results.warning,
+        })
+    }
+}
+
+/// Asks a running daemon, `None` when there is none or it does not answer.
+pub fn ask(request: &SearchRequest) -> Option<Reply> {
+    let stream = UnixStream::connect(socket_path()).ok()?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT)).ok()?;
+    let mut writer = &stream;
+    let mut line = serde_json::to_string(request).ok()?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).ok()?;
+
+    let mut answer = String::new();
+    BufReader::new(&stream).read_line(&mut answer).ok()?;
+    serde_json::from_str(&answer).ok()
+}
+
+/// The index in memory, reloaded when the file on disk changes.
+struct Loaded {
+    searcher: Searcher,
+    modified: Option<SystemTime>,
+}
+
+fn modified_time() -> Option<SystemTime> {
+    fs::metadata(index::get_indeces_path()).and_then(|m| m.modified()).ok()
+}
+
+fn load(collection: &CollectionConfig) -> Result<Loaded, Error> {
+    let modified = modified_time();
+    let searcher = Searcher::load(index::get_indeces_path())?
+        .with_boosts(collection.boosts.clone())
+        .with_analysis(collection.analysis.clone());
+    Ok(Loaded { searcher, modified })
+}
+
+// Answers every request line of a connection until the client hangs up.
+fn handle(stream: UnixStream, loaded: &RwLock<Loaded>, collection: &CollectionConfig) -> io::Result<()> {
+    let mut writer = &stream;
+    for line in BufReader::new(&stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // A new `seroost index` replaces the file, pick it up before answering.
+        let stale = loaded.read().map(|l| l.modified != modified_time()).unwrap_or(true);
+        if stale {
+            match load(collection) {
+                Ok(fresh) => {
+                    println!("{} {}", "Reloaded index with".blue(), format!("{} documents", fresh.searcher.index().len()).yellow());
+                    if let Ok(mut current) = loaded.write() {
+                        *current = fresh;
+                    }
+                }
+                Err(e) => eprintln!("{} {e}", "Error reloading the index:".red()),
+            }
+        }
+
+        let reply = match serde_json::from_str::<SearchRequest>(&line) {
+            Ok(request) => match loaded.read() {
+                Ok(loaded) => match request.run(&loaded.searcher) {
+                    Ok(response) => Reply::Results(response),
+                    Err(err) => Reply::Error(err.to_string()),
+                },
+                Err(_) => Reply::Error("the index is unavailable".to_string()),
+            },
+            Err(e) => Reply::Error(format!("invalid request: {e}")),
+        };
+        let mut answer = serde_json::to_string(&reply)?;
+        answer.push('\n');
+        writer.write_all(answer.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Keeps the index in memory and answers newline-delimited JSON searches on a Unix socket.
+pub fn run(collection: CollectionConfig) -> Result<(), Error> {
+    let path = socket_path();
+    if UnixStream::connect(&path).is_ok() {
+        return Err(io::Error::other(format!("a daemon is already listening on {}", path.display())).into());
+    }
+    // A socket left behind by a daemon that did not shut down cleanly.
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+
+    let loaded = Arc::new(RwLock::new(load(&collection)?));
+    let collection = Arc::new(collection);
+    let listener = UnixListener::bind(&path)?;
+    if let Ok(loaded) = loaded.read() {
+        println!(
+            "{} {} {}",
+            "Serving".green().bold(),
+            loaded.searcher.index().len().to_string().yellow().bold(),
+            format!("documents on {}", path.display()).green().bold()
+        );
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("{} {e}", "Error accepting a connection:".red());
+                continue;
+            }
+        };
+        let (loaded, collection) = (Arc::clone(&loaded), Arc::clone(&collection));
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, &loaded, &collection) {
+                eprintln!("{} {e}", "Error answering a client:".red());
+            }
+        });
+    }
+    Ok(())
+}
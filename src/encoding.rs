@@ -0,0 +1,139 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Text decoded from raw bytes.
+#[derive(Debug)]
+pub struct Decoded {
+    pub text: String,
+    /// Name of the encoding that was used, e.g. `UTF-8` or `windows-1252`.
+    pub encoding: &'static str,
+    /// True when some bytes were invalid and got replaced by U+FFFD.
+    pub lossy: bool,
+}
+
+/// Guesses whether BOM-less bytes are UTF-16 from the position of the zero bytes.
+///
+/// Text that is mostly ASCII has a zero in every other byte when encoded as UTF-16.
+pub fn detect_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let pairs = bytes.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let even_zeros = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if odd_zeros * 2 > pairs && even_zeros * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 2 > pairs && odd_zeros * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Decodes bytes of unknown encoding.
+///
+/// A byte order mark wins, then BOM-less UTF-16, then UTF-8 if the bytes are valid,
+/// then the legacy encoding guessed by `chardetng`. If that guess still fails the
+/// text is decoded as lossy UTF-8.
+pub fn decode(bytes: &[u8]) -> Decoded {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, lossy) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return Decoded {
+            text: text.into_owned(),
+            encoding: encoding.name(),
+            lossy,
+        };
+    }
+
+    // Checked before UTF-8 because ASCII text in UTF-16 is also valid UTF-8.
+    let utf16 = detect_utf16(bytes);
+    if utf16.is_none() {
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return Decoded {
+                text: text.to_string(),
+                encoding: UTF_8.name(),
+                lossy: false,
+            };
+        }
+    }
+
+    let encoding = utf16.unwrap_or_else(|| {
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        detector.guess(None, true)
+    });
+    let (text, lossy) = encoding.decode_without_bom_handling(bytes);
+    if !lossy {
+        return Decoded {
+            text: text.into_owned(),
+            encoding: encoding.name(),
+            lossy,
+        };
+    }
+
+    Decoded {
+        text: String::from_utf8_lossy(bytes).into_owned(),
+        encoding: UTF_8.name(),
+        lossy: true,
+    }
+}
+
+/// Reads a whole file and decodes it with `decode`.
+pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Decoded> {
+    let bytes = fs::read(path)?;
+    Ok(decode(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn keeps_valid_utf8() {
+        let decoded = decode("naïve café".as_bytes());
+        assert_eq!((decoded.text.as_str(), decoded.encoding, decoded.lossy), ("naïve café", "UTF-8", false));
+    }
+
+    #[test]
+    fn follows_the_byte_order_mark() {
+        let decoded = decode(b"\xEF\xBB\xBFhello");
+        assert_eq!((decoded.text.as_str(), decoded.encoding), ("hello", "UTF-8"));
+
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16le("héllo"));
+        let decoded = decode(&bytes);
+        assert_eq!((decoded.text.as_str(), decoded.encoding), ("héllo", "UTF-16LE"));
+    }
+
+    #[test]
+    fn detects_utf16_without_a_byte_order_mark() {
+        let bytes = utf16le("plain ascii text in utf-16");
+        assert_eq!(detect_utf16(&bytes), Some(encoding_rs::UTF_16LE));
+        let decoded = decode(&bytes);
+        assert_eq!((decoded.text.as_str(), decoded.lossy), ("plain ascii text in utf-16", false));
+        assert_eq!(detect_utf16(b"plain ascii text"), None);
+    }
+
+    #[test]
+    fn guesses_legacy_encodings() {
+        let text = "Le café était déjà très animé, à côté de la fenêtre où l'élève étudiait.";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(text);
+        let decoded = decode(&bytes);
+        assert_eq!((decoded.text.as_str(), decoded.lossy), (text, false));
+        assert_ne!(decoded.encoding, "UTF-8");
+    }
+
+    #[test]
+    fn replaces_invalid_bytes_when_nothing_fits() {
+        let decoded = decode(b"\xEF\xBB\xBFok \xFF\xFE end");
+        assert!(decoded.lossy);
+        assert_eq!(decoded.text, "ok \u{FFFD}\u{FFFD} end");
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong in seroost, each kind with its own process exit code.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file, a socket or a terminal failed.
+    Io(io::Error),
+    /// A document could not be parsed, the indexer skips it.
+    Parse(String),
+    /// The configuration file or a command line option is invalid.
+    Config(String),
+    /// The index file cannot be read or was built by another version, it has to be built again.
+    CorruptIndex(String),
+    /// The query has no search terms.
+    Query(String),
+}
+
+/// Shorthand for results of seroost operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The exit code of the process when the error ends it.
+    ///
+    /// | Code | Error |
+    /// | --- | --- |
+    /// | 1 | I/O |
+    /// | 2 | query, like clap's usage errors |
+    /// | 3 | configuration |
+    /// | 4 | corrupt or outdated index |
+    /// | 5 | parse |
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Io(_) => 1,
+            Error::Query(_) => 2,
+            Error::Config(_) => 3,
+            Error::CorruptIndex(_) => 4,
+            Error::Parse(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Parse(message)
+            | Error::Config(message)
+            | Error::CorruptIndex(message)
+            | Error::Query(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<lopdf::Error> for Error {
+    fn from(err: lopdf::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
+
+impl From<xml::reader::Error> for Error {
+    fn from(err: xml::reader::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::AnalysisSettings;
+    use crate::index::Index;
+    use crate::searcher::Searcher;
+    use std::fs;
+
+    #[test]
+    fn every_kind_has_its_own_exit_code() {
+        let errors = [
+            Error::from(io::Error::new(io::ErrorKind::NotFound, "missing")),
+            Error::Query("no terms".to_string()),
+            Error::Config("bad option".to_string()),
+            Error::CorruptIndex("old version".to_string()),
+            Error::Parse("bad pdf".to_string()),
+        ];
+        assert_eq!(errors.map(|error| error.exit_code()), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn failures_surface_as_the_matching_kind() {
+        let path = std::env::temp_dir().join(format!("seroost-error-{}.json", std::process::id()));
+        fs::write(&path, "not an index").unwrap();
+        assert_eq!(Searcher::load(&path).err().map(|error| error.exit_code()), Some(4));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Searcher::load(&path).err().map(|error| error.exit_code()), Some(1));
+
+        let searcher = Searcher::new(Index::new(AnalysisSettings::default()));
+        let error = searcher.search_text("!!!", false, &Default::default(), 10, 0).err();
+        assert_eq!(error.map(|error| error.exit_code()), Some(2));
+    }
+}
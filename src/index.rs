@@ -0,0 +1,578 @@
+use crate::analysis::{AnalysisSettings, Analyzer, Term};
+use crate::error::Error;
+use crate::parsers::{self, Field, ParsedDocument};
+use crate::query::{Clause, Query};
+use crate::regions;
+use crate::registry::{Detection, ParserRegistry};
+use crate::symbols::Symbol;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where a term occurs: its position in the token stream of the field and where its
+/// token is in the field text. Stored as a compact `[position, start, end, line, col]` array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "[u32; 5]", into = "[u32; 5]")]
+pub struct Occurrence {
+    pub position: u32,
+    /// Byte offsets of the token, `start..end`.
+    pub start: u32,
+    pub end: u32,
+    /// 1-based line and column (in characters) of the token.
+    pub line: u32,
+    pub col: u32,
+}
+
+impl From<[u32; 5]> for Occurrence {
+    fn from([position, start, end, line, col]: [u32; 5]) -> Self {
+        Self { position, start, end, line, col }
+    }
+}
+
+impl From<Occurrence> for [u32; 5] {
+    fn from(occurrence: Occurrence) -> Self {
+        [
+            occurrence.position,
+            occurrence.start,
+            occurrence.end,
+            occurrence.line,
+            occurrence.col,
+        ]
+    }
+}
+
+impl From<&Term> for Occurrence {
+    fn from(term: &Term) -> Self {
+        Self {
+            position: term.position,
+            start: term.span.start as u32,
+            end: term.span.end as u32,
+            line: term.span.line,
+            col: term.span.col,
+        }
+    }
+}
+
+/// Every occurrence of a term in ascending position order, the term frequency is their number.
+pub type TermPositions = HashMap<String, Vec<Occurrence>>;
+
+/// Bumped whenever the on-disk layout of the index changes.
+pub const INDEX_VERSION: u32 = 11;
+
+/// Terms of one field of a document.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FieldIndex {
+    /// Total number of terms in the field, used for TF normalization.
+    pub len: usize,
+    pub terms: TermPositions,
+}
+
+impl FieldIndex {
+    /// Where a clause occurs: every occurrence of the term, or of the first term of the phrase
+    /// wherever the whole phrase follows.
+    fn matches(&self, clause: &Clause) -> Vec<Occurrence> {
+        let Some((first, rest)) = clause.terms.split_first() else {
+            return Vec::new();
+        };
+        let Some(starts) = self.terms.get(&first.text) else {
+            return Vec::new();
+        };
+        let mut following = Vec::with_capacity(rest.len());
+        for term in rest {
+            match self.terms.get(&term.text) {
+                Some(occurrences) => following.push((term.position, occurrences)),
+                None => return Vec::new(),
+            }
+        }
+        // Positions are stored in ascending order.
+        starts
+            .iter()
+            .filter(|start| {
+                following.iter().all(|(offset, occurrences)| {
+                    occurrences
+                        .binary_search_by_key(&(start.position + offset), |o| o.position)
+                        .is_ok()
+                })
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Counts how often a clause occurs: the term frequency, or the number of places the phrase starts at.
+    fn occurrences(&self, clause: &Clause) -> usize {
+        match clause.terms.as_slice() {
+            [term] => self.terms.get(&term.text).map_or(0, Vec::len),
+            _ => self.matches(clause).len(),
+        }
+    }
+}
+
+/// A line of a document's body with the query terms found on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineMatch {
+    /// 1-based line number.
+    pub line: u32,
+    /// 1-based column, in characters, of the first match on the line.
+    #[serde(default)]
+    pub col: u32,
+    /// The matching clauses, as their analyzed terms.
+    pub terms: Vec<String>,
+    /// The fields the matches are in: `body`, or the `code`, `comment` and `string` regions of code files.
+    pub fields: Vec<Field>,
+    /// The text of the line, read from the file by the callers that show it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DocumentIndex {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub fields: HashMap<Field, FieldIndex>,
+    /// Definitions found in a code file, in the order of the symbols field.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<Symbol>,
+}
+
+impl DocumentIndex {
+    fn contains(&self, clause: &Clause) -> bool {
+        self.fields
+            .iter()
+            .filter(|(field, _)| clause.field.is_none_or(|wanted| wanted == **field))
+            .any(|(_, field_index)| field_index.occurrences(clause) > 0)
+    }
+
+    /// The symbols matching a clause, with whether the whole name matched (`parse` for
+    /// `parse`, rather than for `parse_query`).
+    fn definitions(&self, clause: &Clause) -> Vec<(&Symbol, bool)> {
+        let Some(field_index) = self.fields.get(&Field::Symbols) else {
+            return Vec::new();
+        };
+        let mut definitions: Vec<(&Symbol, bool)> = Vec::new();
+        for occurrence in field_index.matches(clause) {
+            let Some(symbol) = self.symbols.get(occurrence.line as usize - 1) else {
+                continue;
+            };
+            let exact = occurrence.col == 1 && (occurrence.end - occurrence.start) as usize == symbol.name.len();
+            match definitions.iter_mut().find(|(known, _)| std::ptr::eq(*known, symbol)) {
+                Some((_, known_exact)) => *known_exact |= exact,
+                None => definitions.push((symbol, exact)),
+            }
+        }
+        definitions
+    }
+
+    /// Byte ranges of the query matches in the body text, sorted and merged, a phrase
+    /// spans from its first to its last word. Code files are matched across their regions,
+    /// which share the offsets of the body.
+    pub fn text_matches(&self, query: &Query) -> Vec<(u32, u32)> {
+        let mut ranges = Vec::new();
+        for field in [Field::Body, Field::Code, Field::Comment, Field::String] {
+            let Some(field_index) = self.fields.get(&field) else {
+                continue;
+            };
+            for clause in &query.clauses {
+                if clause.field.is_some_and(|wanted| wanted != field) {
+                    continue;
+                }
+                for start in field_index.matches(clause) {
+                    let end = clause
+                        .terms
+                        .iter()
+                        .filter_map(|term| {
+                            let occurrences = field_index.terms.get(&term.text)?;
+                            let position = start.position + term.position;
+                            let i = occurrences.binary_search_by_key(&position, |o| o.position).ok()?;
+                            Some(occurrences[i].end)
+                        })
+                        .max()
+                        .unwrap_or(start.end);
+                    ranges.push((start.start, end.max(start.end)));
+                }
+            }
+        }
+        ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// The lines of the body matching the query, in line order, from the stored term positions.
+    pub fn line_matches(&self, query: &Query) -> Vec<LineMatch> {
+        let mut lines: BTreeMap<u32, LineMatch> = BTreeMap::new();
+        for field in [Field::Body, Field::Code, Field::Comment, Field::String] {
+            let Some(field_index) = self.fields.get(&field) else {
+                continue;
+            };
+            for clause in &query.clauses {
+                if clause.field.is_some_and(|wanted| wanted != field) {
+                    continue;
+                }
+                let text = clause
+                    .terms
+                    .iter()
+                    .map(|term| term.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                for occurrence in field_index.matches(clause) {
+                    let line_match = lines.entry(occurrence.line).or_insert_with(|| LineMatch {
+                        line: occurrence.line,
+                        col: occurrence.col,
+                        terms: Vec::new(),
+                        fields: Vec::new(),
+                        content: None,
+                    });
+                    line_match.col = line_match.col.min(occurrence.col);
+                    if !line_match.terms.contains(&text) {
+                        line_match.terms.push(text.clone());
+                    }
+                    if !line_match.fields.contains(&field) {
+                        line_match.fields.push(field);
+                    }
+                }
+            }
+        }
+        lines.into_values().collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Index {
+    pub version: u32,
+    /// The analysis the documents went through, queries are analyzed the same way.
+    pub analysis: AnalysisSettings,
+    pub documents: HashMap<PathBuf, DocumentIndex>,
+}
+
+/// A ranked search result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub path: PathBuf,
+    pub score: f64,
+    pub title: Option<String>,
+    /// Symbols matching the `def:` clauses of the query, whole-name matches first.
+    pub definitions: Vec<Symbol>,
+    /// The matching lines, filled in only for the hits that get displayed.
+    #[serde(default)]
+    pub line_matches: Vec<LineMatch>,
+}
+
+impl SearchHit {
+    /// Reads the text of the matching lines when the hit is a code or plain text file, whose
+    /// lines are the lines of the file. Returns whether they were read.
+    pub fn read_lines(&mut self, registry: &ParserRegistry) -> bool {
+        let keeps_lines = matches!(
+            registry.detect(&self.path),
+            Ok(Detection::Parser(parser)) if parser.keeps_lines()
+        );
+        if !keeps_lines {
+            return false;
+        }
+        let Ok(content) = parsers::read_text_file(&self.path) else {
+            return false;
+        };
+        let lines = content.lines().collect::<Vec<_>>();
+        for line_match in &mut self.line_matches {
+            let line = lines.get(line_match.line as usize - 1).copied().unwrap_or_default();
+            line_match.content = Some(line.to_string());
+        }
+        true
+    }
+}
+
+impl Index {
+    pub fn new(analysis: AnalysisSettings) -> Self {
+        Self {
+            version: INDEX_VERSION,
+            analysis,
+            documents: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Sizes of the index: documents, distinct terms, tokens, and documents per field and extension.
+    pub fn stats(&self) -> serde_json::Value {
+        let mut terms = HashSet::new();
+        let mut tokens = 0;
+        let mut fields: HashMap<&str, usize> = HashMap::new();
+        let mut extensions: HashMap<String, usize> = HashMap::new();
+        for (path, doc) in &self.documents {
+            let ext = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+            *extensions.entry(ext.unwrap_or_default()).or_default() += 1;
+            for (field, field_index) in &doc.fields {
+                *fields.entry(field.name()).or_default() += 1;
+                tokens += field_index.len;
+                terms.extend(field_index.terms.keys());
+            }
+        }
+        serde_json::json!({
+            "version": self.version,
+            "documents": self.len(),
+            "terms": terms.len(),
+            "tokens": tokens,
+            "documents_per_field": fields,
+            "extensions": extensions,
+            "analysis": self.analysis,
+        })
+    }
+
+    /// Analyzes every field of a parsed document and adds it to the index.
+    pub fn add_document(&mut self, path: PathBuf, document: &ParsedDocument, analyzer: &Analyzer) {
+        let mut doc_index = DocumentIndex {
+            title: document.title.clone(),
+            symbols: document.symbols.clone(),
+            ..Default::default()
+        };
+
+        for (field, text) in document.fields() {
+            // Regions are masked out of the body byte for byte, their columns are the body's.
+            let masked = matches!(field, Field::Code | Field::Comment | Field::String);
+            let field_index = doc_index.fields.entry(field).or_default();
+            for term in analyzer.terms(&text, document.tokenizer) {
+                let mut occurrence = Occurrence::from(&term);
+                if masked {
+                    occurrence.col = regions::column(&document.body, occurrence.start as usize);
+                }
+                field_index.terms.entry(term.text).or_default().push(occurrence);
+                field_index.len += 1;
+            }
+        }
+
+        self.documents.insert(path, doc_index);
+    }
+
+    pub fn load<P: AsRef<Path>>(index_path: P) -> Result<Self, Error> {
+        let index_file = fs::File::open(&index_path)?;
+        let reader = io::BufReader::new(index_file);
+        let unreadable = |e: serde_json::Error| {
+            Error::CorruptIndex(format!(
+                "could not read index {}: {e}. Please run index again.",
+                index_path.as_ref().display()
+            ))
+        };
+        // Check the version before the layout so old indexes get a clear message.
+        let value: serde_json::Value = serde_json::from_reader(reader).map_err(unreadable)?;
+        let version = value.get("version").and_then(serde_json::Value::as_u64);
+        if version != Some(INDEX_VERSION as u64) {
+            return Err(Error::CorruptIndex(format!(
+                "index {} was built by a different version of seroost. Please run index again.",
+                index_path.as_ref().display()
+            )));
+        }
+        serde_json::from_value(value).map_err(unreadable)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, index_path: P) -> Result<(), Error> {
+        if let Some(parent) = index_path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let index_file = fs::File::create(index_path)?;
+        serde_json::to_writer(io::BufWriter::new(index_file), self).map_err(io::Error::from)?;
+        Ok(())
+    }
+
+    /// Ranks documents with TF-IDF, where the TF of each field is weighted by its boost.
+    /// A phrase counts as a single term occurring wherever the whole phrase does.
+    /// Documents defining a symbol named by a `def:` clause come first, exact names before partial ones.
+    pub fn search(&self, query: &Query, default_boosts: &HashMap<Field, f64>) -> Vec<SearchHit> {
+        // Calculate document frequencies for IDF
+        let total_docs = self.documents.len() as f64;
+        let document_frequency = query
+            .clauses
+            .iter()
+            .map(|clause| {
+                self.documents
+                    .values()
+                    .filter(|doc| doc.contains(clause))
+                    .count()
+            })
+            .collect::<Vec<_>>();
+
+        let mut hits = Vec::new();
+        let mut tiers = HashMap::new();
+        for (doc_path, doc) in &self.documents {
+            let mut score = 0.0;
+            for (clause, doc_freq) in query.clauses.iter().zip(&document_frequency) {
+                // IDF = log(total docs / docs with term)
+                let idf = (total_docs / (*doc_freq).max(1) as f64).ln();
+
+                for (field, field_index) in &doc.fields {
+                    if clause.field.is_some_and(|wanted| wanted != *field) {
+                        continue;
+                    }
+                    let term_count = field_index.occurrences(clause);
+                    if term_count > 0 {
+                        // TF = term count / total terms in the field
+                        let tf = term_count as f64 / field_index.len as f64;
+                        score += query.boost(*field, default_boosts) * tf * idf;
+                    }
+                }
+            }
+
+            if score > 0.0 {
+                let mut definitions = query
+                    .clauses
+                    .iter()
+                    .filter(|clause| clause.definition)
+                    .flat_map(|clause| doc.definitions(clause))
+                    .collect::<Vec<_>>();
+                definitions.sort_by_key(|(symbol, exact)| (!exact, symbol.line));
+                definitions.dedup_by(|(a, _), (b, _)| std::ptr::eq(*a, *b));
+                // 2 for an exact definition, 1 for a partial one, 0 for mentions only.
+                let tier = definitions.first().map_or(0, |(_, exact)| 1 + *exact as u8);
+                tiers.insert(doc_path, tier);
+                hits.push(SearchHit {
+                    path: doc_path.clone(),
+                    score,
+                    title: doc.title.clone(),
+                    definitions: definitions.into_iter().map(|(symbol, _)| symbol.clone()).collect(),
+                    line_matches: Vec::new(),
+                });
+            }
+        }
+
+        // Sort documents by definitions, then by score
+        hits.sort_by(|hit1, hit2| {
+            tiers[&hit2.path].cmp(&tiers[&hit1.path]).then(
+                hit2.score
+                    .partial_cmp(&hit1.score)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        });
+        hits
+    }
+}
+
+/// The index of a language server workspace, kept apart from the index of the CLI so
+/// editors on different workspaces do not index over each other.
+pub fn workspace_index_path(root: &str) -> PathBuf {
+    let name = root
+        .trim_matches('/')
+        .replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '.', "_");
+    get_indeces_path()
+        .with_file_name("workspaces")
+        .join(name)
+        .join("index.json")
+}
+
+/// Returns the configuration path based on the system used.
+/// If no config path found, it results to directory based index storage.
+pub fn get_indeces_path() -> PathBuf {
+    match dirs::config_dir() {
+        Some(path) => path.join("seroost").join("index.json"),
+        None => PathBuf::from("./indeces/index.json"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::Tokenizer;
+
+    fn index(documents: &[(&str, &str, Tokenizer)]) -> (Index, Analyzer) {
+        let settings = AnalysisSettings::default();
+        let analyzer = Analyzer::new(&settings);
+        let mut index = Index::new(settings);
+        for (path, body, tokenizer) in documents {
+            let mut document = ParsedDocument::new(path);
+            document.body = body.to_string();
+            document.tokenizer = *tokenizer;
+            index.add_document(PathBuf::from(path), &document, &analyzer);
+        }
+        (index, analyzer)
+    }
+
+    fn paths(index: &Index, query: &Query) -> Vec<String> {
+        index
+            .search(query, &HashMap::new())
+            .into_iter()
+            .map(|hit| hit.path.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn phrases_match_across_removed_stop_words() {
+        let (index, analyzer) = index(&[
+            ("wells.txt", "The War of the Worlds, by H. G. Wells", Tokenizer::Prose),
+            ("other.txt", "war worlds apart, war and peace in other worlds", Tokenizer::Prose),
+        ]);
+        let query = Query::parse("\"war of the worlds\"", &analyzer);
+        assert_eq!(paths(&index, &query), ["wells.txt"]);
+        let query = Query::parse("\"war worlds\"", &analyzer);
+        assert_eq!(paths(&index, &query), ["other.txt"]);
+    }
+
+    #[test]
+    fn line_matches_have_character_columns_after_multibyte_text() {
+        let code = "// ünïcödé 😀\nlet 😀 = parse_query(ïnput);\n";
+        let (index, analyzer) = index(&[("main.rs", code, Tokenizer::Code)]);
+        let query = Query::parse("query", &analyzer);
+        let matches = index.documents[Path::new("main.rs")].line_matches(&query);
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].line, matches[0].col), (2, 15));
+        assert_eq!(matches[0].terms, ["query"]);
+    }
+
+    #[test]
+    fn region_matches_have_the_columns_of_the_original_line() {
+        let settings = AnalysisSettings::default();
+        let analyzer = Analyzer::new(&settings);
+        let mut index = Index::new(settings);
+        let mut document = ParsedDocument::new("emoji.rs");
+        document.body = "let s = \"😀😀\"; // note here\n".to_string();
+        document.tokenizer = Tokenizer::Code;
+        let syntax = &crate::languages::Language::by_name("rust").unwrap().syntax;
+        document.regions = regions::classify(&document.body, syntax);
+        index.add_document(PathBuf::from("emoji.rs"), &document, &analyzer);
+
+        let query = Query::parse("comment:here", &analyzer);
+        let matches = index.documents[Path::new("emoji.rs")].line_matches(&query);
+        assert_eq!((matches[0].line, matches[0].col), (1, 23));
+        assert_eq!(matches[0].fields, [Field::Comment]);
+    }
+
+    #[test]
+    fn reads_the_lines_of_code_and_text_files_only() {
+        let dir = std::env::temp_dir().join(format!("seroost-lines-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let registry = ParserRegistry::default();
+        let hit = |name: &str, content: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            let line_match = LineMatch {
+                line: 2,
+                col: 1,
+                terms: Vec::new(),
+                fields: Vec::new(),
+                content: None,
+            };
+            let mut hit = SearchHit {
+                path,
+                score: 1.0,
+                title: None,
+                definitions: Vec::new(),
+                line_matches: vec![line_match],
+            };
+            let read = hit.read_lines(&registry);
+            (read, hit.line_matches[0].content.take())
+        };
+        assert_eq!(hit("notes.txt", "first\nsecond\n"), (true, Some("second".to_string())));
+        assert_eq!(hit("main.rs", "fn main() {\n    run();\n}\n"), (true, Some("    run();".to_string())));
+        assert_eq!(hit("page.html", "<p>first</p>\n<p>second</p>\n"), (false, None));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
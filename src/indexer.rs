@@ -0,0 +1,303 @@
+use crate::analysis::{AnalysisSettings, Analyzer};
+use crate::error::Error;
+use crate::index::Index;
+use crate::parsers::ParsedDocument;
+use crate::registry::{Detection, ParserRegistry};
+use crate::report::IndexReport;
+use crossbeam::channel::{self, unbounded};
+use std::any::Any;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ScopedJoinHandle};
+
+/// The default `--file-size` limit, 25MB.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 25 * 1024 * 1024;
+
+fn traverse_dirs<P: AsRef<Path>>(dir_path: P, sender: channel::Sender<String>) {
+    if let Ok(entries) = fs::read_dir(dir_path) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // Handler directory recursion.
+            if path.is_dir() {
+                // Pass a clone of the sender because it's mpsc;
+                traverse_dirs(path, sender.clone());
+            } else {
+                if path.is_file() {
+                    // Send the path; the indexer hung up when it fails.
+                    if sender.send(path.to_string_lossy().to_string()).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The message a panic was raised with.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("no message")
+}
+
+/// Why a file was left out of the index.
+#[derive(Debug, Clone)]
+pub enum SkipReason {
+    /// Larger than the `--file-size` limit.
+    TooLarge { bytes: u64 },
+    Binary,
+    /// No parser claims its extension, name or content.
+    UnknownType,
+    /// The file could not be read to detect its type.
+    Unreadable(String),
+    /// The parser for its format failed.
+    ParseError { format: String, message: String },
+}
+
+impl SkipReason {
+    /// A short name of the reason, the key it is counted under in the indexing report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SkipReason::TooLarge { .. } => "too_large",
+            SkipReason::Binary => "binary",
+            SkipReason::UnknownType => "unknown_type",
+            SkipReason::Unreadable(_) => "unreadable",
+            SkipReason::ParseError { .. } => "parse_error",
+        }
+    }
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::TooLarge { bytes } => write!(f, "large file ({:.2}MB)", *bytes as f64 / (1024.0 * 1024.0)),
+            SkipReason::Binary => write!(f, "binary file"),
+            SkipReason::UnknownType => write!(f, "do not know how to process this file, couldn't discern its type"),
+            SkipReason::Unreadable(message) => write!(f, "error reading file: {message}"),
+            SkipReason::ParseError { format, message } => write!(f, "error processing {format} file: {message}"),
+        }
+    }
+}
+
+/// What happens to each file while indexing.
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// A document was added to the index, `indexed` counts them so far.
+    Indexed { path: String, indexed: usize },
+    Skipped { path: String, reason: SkipReason },
+    /// A document was indexed despite a problem, like bytes of no known encoding.
+    Warning { path: String, message: String },
+}
+
+/// Receives the progress of indexing from the worker threads.
+pub type Reporter = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Builds an index of a directory on a pool of worker threads.
+///
+/// Nothing is printed or saved: every file is reported to the progress callback, if
+/// one is set, and the finished index is returned to the caller.
+pub struct Indexer {
+    registry: Arc<ParserRegistry>,
+    analysis: AnalysisSettings,
+    max_file_size: u64,
+    report: Option<Reporter>,
+}
+
+impl Indexer {
+    pub fn new(registry: Arc<ParserRegistry>, analysis: AnalysisSettings) -> Self {
+        Self {
+            registry,
+            analysis,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            report: None,
+        }
+    }
+
+    /// Skips files larger than `bytes`.
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Reports every indexed and skipped file as it goes.
+    pub fn on_progress(mut self, report: Reporter) -> Self {
+        self.report = Some(report);
+        self
+    }
+
+    /// Detects the format of a file and parses it, unless it is too large or of no known type.
+    pub fn parse_file<P: AsRef<Path>>(&self, file_path: P) -> Result<ParsedDocument, SkipReason> {
+        let file_path = file_path.as_ref();
+        if let Ok(metadata) = fs::metadata(file_path) {
+            if metadata.len() > self.max_file_size {
+                return Err(SkipReason::TooLarge { bytes: metadata.len() });
+            }
+        }
+
+        // Look the parser up by extension, name or content.
+        let parser = match self.registry.detect(file_path) {
+            Ok(Detection::Parser(parser)) => parser,
+            Ok(Detection::Binary) => return Err(SkipReason::Binary),
+            Ok(Detection::Unknown) => return Err(SkipReason::UnknownType),
+            Err(e) => return Err(SkipReason::Unreadable(e.to_string())),
+        };
+        // A parser that panics on one file must not take the worker and its remaining files down.
+        let mut document = panic::catch_unwind(AssertUnwindSafe(|| parser.parse(file_path)))
+            .unwrap_or_else(|payload| Err(Error::Parse(format!("parser panicked: {}", panic_message(&*payload)))))
+            .map_err(|e| SkipReason::ParseError {
+                format: parser.name().to_string(),
+                message: e.to_string(),
+            })?;
+        document.tokenizer = parser.tokenizer();
+        Ok(document)
+    }
+
+    fn report(&self, progress: Progress) {
+        if let Some(report) = &self.report {
+            report(progress);
+        }
+    }
+
+    /// Reports a skipped file or a warning and records it in the report of the run.
+    fn record(&self, collected: &Mutex<IndexReport>, progress: Progress) {
+        if let Ok(mut collected) = collected.lock() {
+            collected.record(&progress);
+        }
+        self.report(progress);
+    }
+
+    /// Indexes every file under `path`, returning the index and the report of the files left out.
+    pub fn index_dir<P: AsRef<Path>>(&self, path: P) -> Result<(Index, IndexReport), Error> {
+        let path = path.as_ref().to_path_buf();
+        let collected = Mutex::new(IndexReport::new(&path));
+        let root = path.clone();
+
+        // Create sender and reciever channels for directory traversal.
+        let (file_sender, file_reciever) = unbounded::<String>();
+
+        // Create sender and reciever channels for file processing threads.
+        let (processing_sender, processing_reciever) = unbounded::<(String, ParsedDocument)>();
+
+        let index = thread::scope(|scope| {
+            // Directory traversal should happen on its onw thread.
+            let dir_traversal_handle = scope.spawn(move || traverse_dirs(&path, file_sender));
+
+            // TermFrequency Calculation Should happen on its own thread. It returns the term frequency index table.
+            let term_frequency_calc_handle = scope.spawn(|| self.calculate_term_frequency(processing_reciever));
+
+            // Create a pool of worker threads.
+            let mut handles: Vec<ScopedJoinHandle<'_, ()>> = vec![];
+
+            let num_threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2);
+            for _ in 0..num_threads {
+                // CLone file reciever.
+                let file_reciever = file_reciever.clone();
+                // Clone file processing sender.
+                let processing_sender = processing_sender.clone();
+                let collected = &collected;
+                let root = &root;
+                // Spawn a thread.
+                let handle = scope.spawn(move || {
+                    while let Ok(file_path) = file_reciever.recv() {
+                        match self.parse_file(&file_path) {
+                            Ok(mut document) => {
+                                for message in std::mem::take(&mut document.warnings) {
+                                    self.record(collected, Progress::Warning { path: file_path.clone(), message });
+                                }
+                                document.strip_root(root);
+                                // send the parsed document and file_path
+                                if processing_sender.send((file_path, document)).is_err() {
+                                    return;
+                                }
+                            }
+                            Err(reason) => self.record(collected, Progress::Skipped { path: file_path, reason }),
+                        }
+                    }
+                });
+
+                // Add each handle to the array.
+                handles.push(handle);
+            }
+
+            // Drop the original reciever and sender, the workers have their own.
+            drop(file_reciever);
+            drop(processing_sender);
+
+            // Wait for the traversal and all worker threads to complete, a panic in any of them
+            // would leave files out of the index without a word.
+            let mut panicked = dir_traversal_handle.join().is_err();
+            for handle in handles {
+                panicked |= handle.join().is_err();
+            }
+
+            match term_frequency_calc_handle.join() {
+                Ok(index) if !panicked => Ok(index),
+                _ => Err(io::Error::other("an indexing thread panicked")),
+            }
+        })?;
+
+        let mut report = collected.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+        report.indexed = index.len();
+        Ok((index, report))
+    }
+
+    fn calculate_term_frequency(&self, processing_reciever: channel::Receiver<(String, ParsedDocument)>) -> Index {
+        // Create a new empty index first
+        let analyzer = Analyzer::new(&self.analysis);
+        let mut index = Index::new(self.analysis.clone());
+
+        while let Ok((file_path, document)) = processing_reciever.recv() {
+            // Analyze every field of the document and add it to the index.
+            index.add_document(PathBuf::from(&file_path), &document, &analyzer);
+            self.report(Progress::Indexed {
+                path: file_path,
+                indexed: index.len(),
+            });
+        }
+        // Return the index
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{DocumentParser, TextParser};
+
+    struct PanickingParser;
+
+    impl DocumentParser for PanickingParser {
+        fn name(&self) -> &str {
+            "panicking"
+        }
+
+        fn parse(&self, _path: &Path) -> Result<ParsedDocument, Error> {
+            panic!("unexpected input")
+        }
+    }
+
+    #[test]
+    fn a_panicking_parser_skips_only_its_file() {
+        let dir = std::env::temp_dir().join(format!("seroost-indexer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.txt", "b.bad", "c.txt", "d.txt"] {
+            fs::write(dir.join(name), "some words").unwrap();
+        }
+        let mut registry = ParserRegistry::empty();
+        registry.register(TextParser, &["txt"], &[]);
+        registry.register(PanickingParser, &["bad"], &[]);
+
+        let indexer = Indexer::new(Arc::new(registry), AnalysisSettings::default());
+        let (index, report) = indexer.index_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].reason, "parse_error");
+        assert_eq!(report.skipped[0].message, "error processing panicking file: parser panicked: unexpected input");
+    }
+}
@@ -1,19 +1,17 @@
-#![allow(dead_code)]
-
 // Bring modules into scope.
-use crate::parsers;
+use crate::daemon::{self, Reply, SearchRequest};
+use seroost::config::CollectionConfig;
+use seroost::index;
+use seroost::parsers::Field;
+use seroost::registry::ParserRegistry;
+use seroost::report::{self, IndexReport};
+use seroost::searcher::Searcher;
+use seroost::symbols::Symbol;
+use seroost::Error;
 use colored::Colorize;
-use std::collections::HashMap;
-use std::fs;
+use serde::Serialize;
 use std::io;
-use std::path::{Path, PathBuf};
-
-// Define 2 types.
-type TermFreq = HashMap<String, usize>;
-type TermFreqIndex = HashMap<PathBuf, TermFreq>;
-
-// Bring native crates.
-use crate::lexer;
+use std::path::Path;
 
 // Mode Enum
 // This determines how the output will looklike, it defaults to regular.
@@ -22,7 +20,80 @@ pub enum Mode {
     Code,
 }
 
-pub fn display_usage() -> Result<(), parsers::GlobalError> {
+/// Output formats of `search`: the two modes, and formats for other tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Regular,
+    Code,
+    /// `path:line:col:text` for every matching line of code and text files, like `rg --vimgrep`.
+    Vimgrep,
+    /// One JSON object per result.
+    Ndjson,
+    Csv,
+    /// Only the paths of the results.
+    Paths,
+}
+
+impl From<Mode> for Format {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Regular => Format::Regular,
+            Mode::Code => Format::Code,
+        }
+    }
+}
+
+/// The `--mode code` output, one JSON document with every result.
+#[derive(Serialize)]
+struct CodeOutput<'a> {
+    query: &'a str,
+    results: Vec<CodeResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct CodeResult<'a> {
+    rank: usize,
+    path: &'a Path,
+    score: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[Symbol]>::is_empty")]
+    definitions: &'a [Symbol],
+    line_matches: Vec<CodeLine<'a>>,
+}
+
+#[derive(Serialize)]
+struct CodeLine<'a> {
+    line: u32,
+    content: &'a str,
+    terms: &'a [String],
+    regions: &'a [Field],
+}
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// One path per line, or each followed by a NUL byte for `xargs -0`.
+fn path_list<'a>(paths: impl Iterator<Item = &'a Path>, null_separated: bool) -> String {
+    let separator = if null_separated { '\0' } else { '\n' };
+    paths.map(|path| format!("{}{separator}", path.to_string_lossy())).collect()
+}
+
+/// The 1-based byte column of a 1-based character column, as vim and grep count them.
+fn byte_column(line: &str, col: u32) -> usize {
+    line.char_indices()
+        .nth(col.saturating_sub(1) as usize)
+        .map_or(line.len(), |(i, _)| i)
+        + 1
+}
+
+pub fn display_usage() -> Result<(), Error> {
     println!("{}", "═".repeat(80).cyan());
     println!(
         "{}",
@@ -110,7 +181,7 @@ pub fn display_usage() -> Result<(), parsers::GlobalError> {
     );
     println!();
     println!("{}", "Expected output:".bright_blue());
-    println!("  {}Loading search index...{}", "".blue(), "");
+    println!("  {}Loading search index...", "".blue());
     println!(
         "  {}Search results for:{} programming language",
         "".green().bold(),
@@ -118,18 +189,14 @@ pub fn display_usage() -> Result<(), parsers::GlobalError> {
     );
     println!("  {}", "═".repeat(60));
     println!(
-        "  {}1.{} ~/documents/samples/{}rust.txt{} (Score: 0.28768)",
+        "  {}1. ~/documents/samples/{}rust.txt (Score: 0.28768)",
         "".yellow().bold(),
-        "",
         "".green().bold(),
-        ""
     );
     println!(
-        "  {}2.{} ~/documents/samples/{}python.txt{} (Score: 0.14384)",
+        "  {}2. ~/documents/samples/{}python.txt (Score: 0.14384)",
         "".yellow().bold(),
-        "",
         "".green().bold(),
-        ""
     );
     println!("  {}", "═".repeat(60));
     println!();
@@ -140,289 +207,185 @@ pub fn display_usage() -> Result<(), parsers::GlobalError> {
     println!("  {} seroost search \"readability\"", "$".bright_black());
     println!();
 
-    println!("{}", "═".repeat(80).cyan());
-    Ok(())
-}
-
-pub fn process_files(
-    dir_path: &str,
-    term_frequency_index: &mut TermFreqIndex,
-    max_file_size: u64,
-) -> Result<(), parsers::GlobalError> {
-    let dir_content = fs::read_dir(dir_path);
-
-    match dir_content {
-        Ok(entries) => {
-            for entry in entries.flatten() {
-                let path = entry.path();
-
-                // Handle directory recursion - pass the same index to accumulate results
-                if path.is_dir() {
-                    process_files(
-                        path.to_str().unwrap_or(dir_path),
-                        term_frequency_index,
-                        max_file_size,
-                    )?;
-                    continue;
-                }
-                // Print this message to inform the user of a skipped large file.
-                if let Ok(metadata) = fs::metadata(&path) {
-                    let file_size = metadata.len();
-                    if file_size > max_file_size {
-                        println!(
-                            "{} {:?} ({:.2}MB)",
-                            "Skipping large file:".yellow(),
-                            path,
-                            file_size as f64 / (1024.0 * 1024.0)
-                        );
-                        continue;
-                    }
-                }
-
-                // Process files based on extensions.
-                let content: Vec<char>;
-                match path.extension() {
-                    Some(ext) => match ext.to_string_lossy().to_lowercase().as_str() {
-                        "pdf" => match parsers::read_entire_pdf_file(&path) {
-                            Ok(text) => content = text.chars().collect::<Vec<_>>(),
-                            Err(e) => {
-                                eprintln!(
-                                    "{} {:?}: {}",
-                                    "Error processing PDF file:".red(),
-                                    path,
-                                    e
-                                );
-                                continue;
-                            }
-                        },
-                        "txt" => match parsers::read_entire_txt_file(&path) {
-                            Ok(text) => content = text.chars().collect::<Vec<_>>(),
-                            Err(e) => {
-                                eprintln!(
-                                    "{} {:?}: {}",
-                                    "Error processing text file:".red(),
-                                    path,
-                                    e
-                                );
-                                continue;
-                            }
-                        },
-                        "xml" | "xhtml" => match parsers::read_entire_xml_file(&path) {
-                            Ok(text) => content = text.chars().collect::<Vec<_>>(),
-                            Err(e) => {
-                                eprintln!(
-                                    "{} {:?}: {}",
-                                    "Error processing XML file:".red(),
-                                    path,
-                                    e
-                                );
-                                continue;
-                            }
-                        },
-                        "html" | "htm" => match parsers::read_entire_html_file(&path) {
-                            Ok(text) => content = text.chars().collect::<Vec<_>>(),
-                            Err(e) => {
-                                eprintln!(
-                                    "{} {:?}: {}",
-                                    "Error processing HTML file:".red(),
-                                    path,
-                                    e
-                                );
-                                continue;
-                            }
-                        },
-                        "rs" | "py" | "js" | "ts" | "java" | "cpp" | "c" | "h" | "go" | "php"
-                        | "rb" | "swift" | "kt" => match parsers::read_code_file(&path) {
-                            Ok(text) => content = text.chars().collect::<Vec<_>>(),
-                            Err(e) => {
-                                eprintln!(
-                                    "{} {:?}: {}",
-                                    "Error processing code file:".red(),
-                                    path,
-                                    e
-                                );
-                                continue;
-                            }
-                        },
-                        _ => {
-                            eprintln!("Error: do not know how to process file: {path:?}. Skipping file...");
-                            continue;
-                        }
-                    },
-                    None => {
-                        eprintln!(
-                            "{}: do not know how to process this file, couldn't discern the extension: {path:?}
-                             Skipping file...",
-                            "Error".red()
-                        );
-                        continue;
-                    }
-                }
-
-                // Create a hashmap/table for storing the term frequency of each document.
-                let mut term_frequency = TermFreq::new();
-                let lexer = lexer::Lexer::new(&content);
-                for token in lexer {
-                    let term = token;
+    // Symbols
+    println!("{}", "FINDING DEFINITIONS".yellow().bold());
+    println!("Rank the files defining a function, struct, class or constant first:");
+    println!("  {} seroost search --symbol parse", "$".bright_black());
+    println!("  {} seroost search \"def:parse query\"", "$".bright_black());
+    println!();
 
-                    // Check if the term already exists in the HashMap/Table.
-                    if let Some(count) = term_frequency.get_mut(&term) {
-                        *count += 1; // Increment the frequency count of the word in the doc if it already exists.
-                    } else {
-                        // `move` the term into the hashmap/table because it gets dropped after this point regardless.
-                        term_frequency.insert(term, 1); // Start The Term Count Up with a 1
-                    }
-                }
-                // Create a vector containing (term, frequency) sorted in reverse.
-                let mut stats = term_frequency.iter().collect::<Vec<_>>();
-                stats.sort_by_key(|(_, f)| *f);
-                stats.reverse();
-                println!("{} {}", "Indexing:".blue(), path.to_string_lossy().green());
-
-                // Add a file and its term frequency to the term_frequeny_index table.
-                term_frequency_index.insert(path.to_path_buf(), term_frequency);
-            }
-        }
-        Err(_) => {
-            eprintln!("Error could not read dir: {dir_path}");
-        }
-    }
+    // Formats
+    println!("{}", "OUTPUT FORMATS".yellow().bold());
+    println!("Feed results to other tools with --format vimgrep, ndjson, csv or paths:");
+    println!("  {} seroost search parse --format vimgrep", "$".bright_black());
+    println!("  {} seroost search parse --format paths -0 | xargs -0 wc -l", "$".bright_black());
+    println!();
 
+    println!("{}", "═".repeat(80).cyan());
     Ok(())
 }
 
-pub fn index_documents(dir_path: &str, max_file_size: u64) -> Result<(), parsers::GlobalError> {
-    // Create a new empty index first
-    let mut term_frequency_index = TermFreqIndex::new();
-
-    // Process documents and populate the index
-    println!(
-        "{} {}",
-        "Indexing directory:".green().bold(),
-        dir_path.blue()
-    );
-    process_files(dir_path, &mut term_frequency_index, max_file_size)?;
-
-    // Save the complete index only once after all processing is done
-    let index_path: PathBuf = get_indeces_path();
-    if let Some(parent) = Path::new(&index_path).parent() {
-        fs::create_dir_all(parent)?;
+/// Prints every registered format, used by `seroost formats`.
+pub fn display_formats(registry: &ParserRegistry) {
+    println!("{}", "SUPPORTED FORMATS".yellow().bold());
+    println!("{}", "═".repeat(80).cyan());
+    for format in registry.formats() {
+        println!(
+            "{} ({})",
+            format.parser.name().green().bold(),
+            format.parser.kind().name().bright_black()
+        );
+        println!("  {} {}", "Extensions:".blue(), format.extensions.join(", "));
+        if !format.mime_types.is_empty() {
+            println!("  {} {}", "MIME types:".blue(), format.mime_types.join(", "));
+        }
     }
     println!(
-        "{} {}",
-        "Saving index to:".green(),
-        index_path.to_str().expect("Invalid Path Name").blue()
+        "Files without a known extension are detected by name (Makefile, Dockerfile, README...),"
     );
-    let index_file = fs::File::create(index_path)?;
-    serde_json::to_writer(index_file, &term_frequency_index)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-
+    println!("shebang line and content. Other text files are indexed as plain text unless");
     println!(
-        "{} {} {}",
-        "Successfully indexed".green().bold(),
-        term_frequency_index.len().to_string().yellow().bold(),
-        "documents".green().bold()
+        "{} is set to {} in the collection config.",
+        "\"unknown_files\"".green(),
+        "\"skip\"".green()
     );
-
-    Ok(())
+    println!("{}", "═".repeat(80).cyan());
 }
 
-pub fn search_documents(query: &str, output_mode: Mode) -> Result<(), parsers::GlobalError> {
-    // Load the index
-    let index_path = get_indeces_path();
-    if !Path::new(&index_path).exists() {
-        match output_mode {
-            Mode::Regular => {
-                eprintln!(
-                    "{}",
-                    "Error: index file not found. Please run index first."
-                        .red()
-                        .bold()
-                );
-            }
-            Mode::Code => {
-                eprintln!("{{\"error\": \"index file not found. Please run index first.\"}}");
-            }
+/// Prints the skipped files of a report as a table of counts per extension and reason.
+pub fn display_report_summary(report: &IndexReport) {
+    if report.skipped.is_empty() {
+        println!("{}", "No files were skipped.".green());
+        return;
+    }
+    let reasons = report.by_reason();
+    let extensions = report.by_extension();
+    let label = |extension: &str| {
+        if extension.is_empty() {
+            "(none)".to_string()
+        } else {
+            format!(".{extension}")
         }
-        return Ok(());
+    };
+    let width = extensions
+        .keys()
+        .map(|extension| label(extension).len())
+        .chain(["Extension".len()])
+        .max()
+        .unwrap_or_default();
+    let headers = reasons.keys().map(|reason| reason.replace('_', " ")).collect::<Vec<_>>();
+
+    println!("{}", "SKIPPED FILES".yellow().bold());
+    let mut header = format!("{:<width$}", "Extension");
+    for name in headers.iter().map(String::as_str).chain(["total"]) {
+        header.push_str(&format!("  {name:>w$}", w = name.len().max(5)));
     }
-
-    let index_file = fs::File::open(&index_path)?;
-    let reader = std::io::BufReader::new(index_file);
-    let term_frequency_index: TermFreqIndex = serde_json::from_reader(reader)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-
-    match output_mode {
-        Mode::Regular => println!("{}", "Loading search index...".blue()),
-        Mode::Code => {} // No output for code mode
+    println!("{}", header.bold());
+    let row = |name: String, counts: Vec<usize>| {
+        let mut line = format!("{name:<width$}");
+        let total = counts.iter().sum::<usize>();
+        for (count, name) in counts.into_iter().chain([total]).zip(headers.iter().map(String::as_str).chain(["total"])) {
+            line.push_str(&format!("  {count:>w$}", w = name.len().max(5)));
+        }
+        line
+    };
+    for (extension, counts) in &extensions {
+        let counts = reasons.keys().map(|reason| counts.get(reason).copied().unwrap_or_default()).collect();
+        println!("{}", row(label(extension), counts));
     }
+    println!("{}", row("Total".to_string(), reasons.values().copied().collect()).bold());
+}
 
-    // Tokenize the search query
-    let query_chars = query.chars().collect::<Vec<_>>();
-    let lexer = lexer::Lexer::new(&query_chars);
-    let query_terms: Vec<String> = lexer.collect();
-
-    if query_terms.is_empty() {
-        match output_mode {
-            Mode::Regular => println!("{}", "No valid search terms found.".yellow()),
-            Mode::Code => println!("{{\"error\": \"No valid search terms found.\"}}"),
-        }
+/// Shows the report of the last indexing run, as JSON for tools when `json` is set.
+pub fn display_report(json: bool) -> Result<(), Error> {
+    let report_path = report::get_report_path();
+    if !report_path.exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "report not found. Please run index first.").into());
+    }
+    let report = IndexReport::load(&report_path)?;
+    if json {
+        println!("{}", report.summary());
         return Ok(());
     }
 
-    // Calculate document frequencies for IDF
-    let total_docs = term_frequency_index.len() as f64;
-    let mut document_frequency: HashMap<String, usize> = HashMap::new();
-
-    // Count documents containing each term
-    for term in &query_terms {
-        for (_, doc_term_freq) in &term_frequency_index {
-            if doc_term_freq.contains_key(term) {
-                *document_frequency.entry(term.clone()).or_insert(0) += 1;
-            }
-        }
+    println!(
+        "{} {} {} {}",
+        "Indexed".green().bold(),
+        report.indexed.to_string().yellow().bold(),
+        "documents in".green().bold(),
+        report.root.blue()
+    );
+    for file in &report.skipped {
+        println!("{} {:?}: {}", "Skipped".yellow(), file.path, file.message);
     }
+    for warning in &report.warnings {
+        println!("{} {:?}: {}", "Warning".yellow(), warning.path, warning.message);
+    }
+    println!();
+    display_report_summary(&report);
+    Ok(())
+}
 
-    // Calculate TF-IDF scores for each document
-    let mut document_scores: HashMap<PathBuf, f64> = HashMap::new();
-
-    for (doc_path, term_freq) in &term_frequency_index {
-        // Calculate total terms in document for TF normalization
-        let total_terms: usize = term_freq.values().sum();
-        let total_terms = total_terms as f64;
-
-        // Calculate TF-IDF score for each query term in this document
-        let mut score = 0.0;
-        for term in &query_terms {
-            if let Some(&term_count) = term_freq.get(term) {
-                // TF = term count / total terms
-                let tf = term_count as f64 / total_terms;
-
-                // IDF = log(total docs / docs with term)
-                let doc_freq = document_frequency.get(term).unwrap_or(&1);
-                let idf = (total_docs / *doc_freq as f64).ln();
+pub fn search_documents(
+    query: &str,
+    symbol: bool,
+    limit: usize,
+    format: Format,
+    null_separated: bool,
+    collection: &CollectionConfig,
+    registry: &ParserRegistry,
+) -> Result<(), Error> {
+    let request = SearchRequest {
+        query: query.to_string(),
+        symbol,
+        limit,
+        offset: 0,
+    };
+
+    // A running daemon already has the index in memory, otherwise load it here.
+    let reply = match daemon::ask(&request) {
+        Some(reply) => reply,
+        None => {
+            let index_path = index::get_indeces_path();
+            if !Path::new(&index_path).exists() {
+                let message = "index file not found. Please run index first.";
+                if let Format::Code | Format::Ndjson = format {
+                    eprintln!("{}", serde_json::json!({ "error": message }));
+                }
+                return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+            }
 
-                // TF-IDF = TF * IDF
-                score += tf * idf;
+            if format == Format::Regular {
+                println!("{}", "Loading search index...".blue());
+            }
+            let searcher = Searcher::load(&index_path)?
+                .with_boosts(collection.boosts.clone())
+                .with_analysis(collection.analysis.clone());
+            match request.run(&searcher) {
+                Ok(response) => Reply::Results(response),
+                Err(err) => Reply::Error(err.to_string()),
             }
         }
-
-        if score > 0.0 {
-            document_scores.insert(doc_path.clone(), score);
+    };
+
+    let response = match reply {
+        Reply::Results(response) => response,
+        Reply::Error(message) => {
+            // Tools reading JSON get the error as JSON, on stderr like every other error.
+            if let Format::Code | Format::Ndjson = format {
+                eprintln!("{}", serde_json::json!({ "error": message }));
+            }
+            return Err(Error::Query(message));
         }
+    };
+    if let Some(warning) = &response.warning {
+        eprintln!("{}", format!("Warning: {warning}").yellow());
     }
+    let mut ranked_docs = response.results;
 
-    // Sort documents by score
-    let mut ranked_docs: Vec<(&PathBuf, &f64)> = document_scores.iter().collect();
-    ranked_docs.sort_by(|(_, score1), (_, score2)| {
-        score2
-            .partial_cmp(score1)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
-
-    // Display results based on mode
-    match output_mode {
-        Mode::Regular => {
+    // Display results based on the format
+    match format {
+        Format::Regular => {
             println!(
                 "{} {}",
                 "Search results for:".green().bold(),
@@ -433,7 +396,8 @@ pub fn search_documents(query: &str, output_mode: Mode) -> Result<(), parsers::G
                 println!("{}", "No matching documents found.".yellow());
             } else {
                 println!("{}", "═".repeat(60).cyan());
-                for (i, (path, score)) in ranked_docs.iter().take(10).enumerate() {
+                for (i, hit) in ranked_docs.iter().enumerate() {
+                    let (path, score) = (&hit.path, hit.score);
                     let filename = path.file_name().unwrap_or_default().to_string_lossy();
 
                     // Format ranking number
@@ -448,104 +412,145 @@ pub fn search_documents(query: &str, output_mode: Mode) -> Result<(), parsers::G
                     let score_str = format!("Score: {:.5}", score).bright_blue();
 
                     println!("{} {} ({})", rank, colorized_path, score_str);
+                    if let Some(title) = &hit.title {
+                        println!("   {}", title.bright_black());
+                    }
+                    // Which regions of a code file matched.
+                    let mut regions = hit
+                        .line_matches
+                        .iter()
+                        .flat_map(|line_match| line_match.fields.iter().copied())
+                        .filter(|field| *field != Field::Body)
+                        .map(|field| field.name())
+                        .collect::<Vec<_>>();
+                    regions.sort_unstable();
+                    regions.dedup();
+                    if !regions.is_empty() {
+                        println!("   {} {}", "matched in".bright_black(), regions.join(", ").cyan());
+                    }
+                    for definition in &hit.definitions {
+                        println!(
+                            "   {} {} {}",
+                            definition.kind.name().magenta(),
+                            definition.name.bold(),
+                            format!("(line {})", definition.line).bright_black()
+                        );
+                    }
                 }
                 println!("{}", "═".repeat(60).cyan());
             }
         }
-        Mode::Code => {
-            if ranked_docs.is_empty() {
-                println!("{{\"query\": \"{}\", \"results\": []}}", query);
-            } else {
-                println!("{{");
-                println!("  \"query\": \"{}\",", query);
-                println!("  \"results\": [");
-
-                for (i, (path, score)) in ranked_docs.iter().take(10).enumerate() {
-                    let path_str = path.to_string_lossy();
-                    println!("    {{");
-                    println!("      \"rank\": {},", i + 1);
-                    println!("      \"path\": \"{}\",", path_str);
-                    println!("      \"score\": {:.5},", score);
-
-                    // Check if it's a code file and get line information
-                    if let Some(ext) = path.extension() {
-                        let ext_str = ext.to_string_lossy().to_lowercase();
-                        if matches!(
-                            ext_str.as_str(),
-                            "rs" | "py"
-                                | "js"
-                                | "ts"
-                                | "java"
-                                | "cpp"
-                                | "c"
-                                | "h"
-                                | "go"
-                                | "php"
-                                | "rb"
-                                | "swift"
-                                | "kt"
-                        ) {
-                            match parsers::get_code_line_info(path, query) {
-                                Ok(line_matches) => {
-                                    println!("      \"line_matches\": [");
-                                    for (j, (line_num, line_content)) in
-                                        line_matches.iter().enumerate()
-                                    {
-                                        let line_content_escaped =
-                                            line_content.replace("\"", "\\\"").replace("\n", "\\n");
-                                        print!(
-                                            "        {{\"line\": {}, \"content\": \"{}\"}}",
-                                            line_num, line_content_escaped
-                                        );
-                                        if j < line_matches.len() - 1 {
-                                            println!(",");
-                                        } else {
-                                            println!();
-                                        }
-                                    }
-                                    println!("      ]");
-                                }
-                                Err(_) => {
-                                    println!("      \"line_matches\": []");
-                                }
-                            }
-                        } else {
-                            println!("      \"line_matches\": []");
-                        }
-                    } else {
-                        println!("      \"line_matches\": []");
-                    }
-
-                    print!("    }}");
-                    if i < ranked_docs.len() - 1 && i < 9 {
-                        println!(",");
+        Format::Code => {
+            // The matching lines come from the index, the file is only read for their text.
+            let has_lines = ranked_docs
+                .iter_mut()
+                .map(|hit| hit.read_lines(registry))
+                .collect::<Vec<_>>();
+            let results = ranked_docs
+                .iter()
+                .zip(has_lines)
+                .enumerate()
+                .map(|(i, (hit, has_lines))| CodeResult {
+                    rank: i + 1,
+                    path: &hit.path,
+                    score: hit.score,
+                    title: hit.title.as_deref(),
+                    definitions: &hit.definitions,
+                    line_matches: if has_lines {
+                        hit.line_matches
+                            .iter()
+                            .map(|line_match| CodeLine {
+                                line: line_match.line,
+                                content: line_match.content.as_deref().unwrap_or_default(),
+                                terms: &line_match.terms,
+                                regions: &line_match.fields,
+                            })
+                            .collect()
                     } else {
-                        println!();
-                    }
+                        Vec::new()
+                    },
+                })
+                .collect();
+            let output = CodeOutput { query, results };
+            println!("{}", serde_json::to_string_pretty(&output).map_err(io::Error::from)?);
+        }
+        Format::Vimgrep => {
+            for hit in &mut ranked_docs {
+                // Only code and plain text files have lines of the file to jump to, the matches
+                // in text extracted from other documents are left out.
+                if !hit.read_lines(registry) {
+                    continue;
+                }
+                let path = hit.path.to_string_lossy();
+                for line_match in &hit.line_matches {
+                    let content = line_match.content.as_deref().unwrap_or_default();
+                    let col = byte_column(content, line_match.col);
+                    println!("{path}:{}:{col}:{content}", line_match.line);
+                }
+            }
+        }
+        Format::Ndjson => {
+            for (i, hit) in ranked_docs.iter_mut().enumerate() {
+                hit.read_lines(registry);
+                let mut value = serde_json::to_value(&*hit).map_err(io::Error::from)?;
+                if let Some(object) = value.as_object_mut() {
+                    object.insert("rank".to_string(), serde_json::Value::from(i + 1));
                 }
-                println!("  ]");
-                println!("}}");
+                println!("{value}");
             }
         }
+        Format::Csv => {
+            println!("rank,path,score,title,definitions,lines");
+            for (i, hit) in ranked_docs.iter().enumerate() {
+                let definitions = hit
+                    .definitions
+                    .iter()
+                    .map(|symbol| format!("{} {}:{}", symbol.kind, symbol.name, symbol.line))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                let lines = hit
+                    .line_matches
+                    .iter()
+                    .map(|line_match| line_match.line.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                println!(
+                    "{},{},{:.5},{},{},{}",
+                    i + 1,
+                    csv_field(&hit.path.to_string_lossy()),
+                    hit.score,
+                    csv_field(hit.title.as_deref().unwrap_or_default()),
+                    csv_field(&definitions),
+                    lines
+                );
+            }
+        }
+        Format::Paths => {
+            print!("{}", path_list(ranked_docs.iter().map(|hit| hit.path.as_path()), null_separated));
+        }
     }
 
     Ok(())
 }
 
-/// Returns the configuration path based on the system used.
-/// If no config path found, it results to directory based config storage.
-pub fn get_config_path() -> PathBuf {
-    match dirs::config_dir() {
-        Some(path) => path.join("seroost").join("config.json"),
-        None => PathBuf::from("./indeces/config.json"),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(csv_field("docs/readme.md"), "docs/readme.md");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field(""), "");
     }
-}
 
-/// Returns the configuration path based on the system used.
-/// If no config path found, it results to directory based index storage.
-pub fn get_indeces_path() -> PathBuf {
-    match dirs::config_dir() {
-        Some(path) => path.join("seroost").join("index.json"),
-        None => PathBuf::from("./indeces/index.json"),
+    #[test]
+    fn separates_paths_with_new_lines_or_nul_bytes() {
+        let paths = [Path::new("docs/a b.txt"), Path::new("odd\nname.txt")];
+        assert_eq!(path_list(paths.into_iter(), false), "docs/a b.txt\nodd\nname.txt\n");
+        assert_eq!(path_list(paths.into_iter(), true), "docs/a b.txt\0odd\nname.txt\0");
+        assert_eq!(path_list(std::iter::empty(), true), "");
     }
 }
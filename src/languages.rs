@@ -0,0 +1,286 @@
+use crate::regions::Syntax;
+use crate::symbols;
+
+/// A programming or configuration language indexed as code.
+#[derive(Debug)]
+pub struct Language {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub mime_types: &'static [&'static str],
+    /// How comments and strings are written, used to split files into regions.
+    pub syntax: Syntax,
+    /// How definitions are written, `None` falls back to the keywords most languages share.
+    pub symbols: Option<symbols::Syntax>,
+}
+
+impl Language {
+    /// Finds a language by name, ignoring case (`rust`, `C++`).
+    pub fn by_name(name: &str) -> Option<&'static Language> {
+        LANGUAGES
+            .iter()
+            .find(|language| language.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn for_extension(ext: &str) -> Option<&'static Language> {
+        let ext = ext.to_lowercase();
+        LANGUAGES
+            .iter()
+            .find(|language| language.extensions.contains(&ext.as_str()))
+    }
+
+    /// Finds a language by the MIME type sniffed from a file's name or shebang line.
+    pub fn for_mime_type(mime_type: &str) -> Option<&'static Language> {
+        LANGUAGES
+            .iter()
+            .find(|language| language.mime_types.iter().any(|known| known.eq_ignore_ascii_case(mime_type)))
+    }
+}
+
+const C_LIKE: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\"", "'"],
+};
+
+// `'` starts lifetimes in Rust, so only double quotes delimit strings.
+const RUST: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\""],
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\"", "'", "`"],
+};
+
+const GO: Syntax = JAVASCRIPT;
+
+const TRIPLE_QUOTED: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\"\"\"", "\"", "'"],
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    strings: &["\"\"\"", "'''", "\"", "'"],
+};
+
+const RUBY: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[("=begin", "=end")],
+    strings: &["\"", "'"],
+};
+
+const PHP: Syntax = Syntax {
+    line_comments: &["//", "#"],
+    block_comments: &[("/*", "*/")],
+    strings: &["\"", "'"],
+};
+
+// Shell, Perl, TOML, YAML, Makefiles and Dockerfiles.
+const HASH_COMMENTS: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    strings: &["\"", "'"],
+};
+
+const LUA: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("--[[", "]]")],
+    strings: &["\"", "'"],
+};
+
+const SQL: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("/*", "*/")],
+    strings: &["'", "\""],
+};
+
+const HASKELL: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comments: &[("{-", "-}")],
+    strings: &["\""],
+};
+
+/// Every language seroost knows, the extensions are what the code parser is registered for.
+pub const LANGUAGES: &[Language] = &[
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        mime_types: &["text/x-rust"],
+        syntax: RUST,
+        symbols: Some(symbols::Syntax::Rust),
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyi", "pyw"],
+        mime_types: &["text/x-python"],
+        syntax: PYTHON,
+        symbols: Some(symbols::Syntax::Python),
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs", "jsx"],
+        mime_types: &["text/javascript"],
+        syntax: JAVASCRIPT,
+        symbols: Some(symbols::Syntax::JavaScript),
+    },
+    Language {
+        name: "TypeScript",
+        extensions: &["ts", "tsx", "mts", "cts"],
+        mime_types: &["application/typescript"],
+        syntax: JAVASCRIPT,
+        symbols: Some(symbols::Syntax::JavaScript),
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        mime_types: &["text/x-java"],
+        syntax: C_LIKE,
+        symbols: Some(symbols::Syntax::Java),
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        mime_types: &["text/x-csharp"],
+        syntax: C_LIKE,
+        symbols: Some(symbols::Syntax::Java),
+    },
+    Language {
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        mime_types: &["text/x-kotlin"],
+        syntax: TRIPLE_QUOTED,
+        symbols: Some(symbols::Syntax::Kotlin),
+    },
+    Language {
+        name: "Scala",
+        extensions: &["scala", "sc"],
+        mime_types: &["text/x-scala"],
+        syntax: TRIPLE_QUOTED,
+        symbols: Some(symbols::Syntax::Scala),
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        mime_types: &["text/x-swift"],
+        syntax: TRIPLE_QUOTED,
+        symbols: Some(symbols::Syntax::Swift),
+    },
+    Language {
+        name: "Dart",
+        extensions: &["dart"],
+        mime_types: &[],
+        syntax: TRIPLE_QUOTED,
+        symbols: Some(symbols::Syntax::Java),
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        mime_types: &["text/x-go"],
+        syntax: GO,
+        symbols: Some(symbols::Syntax::Go),
+    },
+    Language {
+        name: "C",
+        extensions: &["c", "h"],
+        mime_types: &["text/x-csrc", "text/x-chdr"],
+        syntax: C_LIKE,
+        symbols: Some(symbols::Syntax::C),
+    },
+    Language {
+        name: "C++",
+        extensions: &["cpp", "cc", "cxx", "hpp", "hh", "hxx"],
+        mime_types: &["text/x-c++src", "text/x-c++hdr"],
+        syntax: C_LIKE,
+        symbols: Some(symbols::Syntax::C),
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php"],
+        mime_types: &["application/x-httpd-php"],
+        syntax: PHP,
+        symbols: Some(symbols::Syntax::Php),
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb"],
+        mime_types: &["text/x-ruby"],
+        syntax: RUBY,
+        symbols: Some(symbols::Syntax::Ruby),
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        mime_types: &["text/x-lua"],
+        syntax: LUA,
+        symbols: None,
+    },
+    Language {
+        name: "Zig",
+        extensions: &["zig"],
+        mime_types: &[],
+        syntax: C_LIKE,
+        symbols: None,
+    },
+    Language {
+        name: "Haskell",
+        extensions: &["hs"],
+        mime_types: &["text/x-haskell"],
+        syntax: HASKELL,
+        symbols: None,
+    },
+    Language {
+        name: "Perl",
+        extensions: &["pl", "pm"],
+        mime_types: &["text/x-perl"],
+        syntax: HASH_COMMENTS,
+        symbols: None,
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh", "fish"],
+        mime_types: &["text/x-shellscript"],
+        syntax: HASH_COMMENTS,
+        symbols: None,
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        mime_types: &["application/sql"],
+        syntax: SQL,
+        symbols: None,
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        mime_types: &["application/toml"],
+        syntax: HASH_COMMENTS,
+        symbols: None,
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yaml", "yml"],
+        mime_types: &["application/yaml"],
+        syntax: HASH_COMMENTS,
+        symbols: None,
+    },
+    Language {
+        name: "Makefile",
+        extensions: &["mk"],
+        mime_types: &["text/x-makefile"],
+        syntax: HASH_COMMENTS,
+        symbols: None,
+    },
+    Language {
+        name: "Dockerfile",
+        extensions: &[],
+        mime_types: &["text/x-dockerfile"],
+        syntax: HASH_COMMENTS,
+        symbols: None,
+    },
+];
@@ -1,59 +1,392 @@
+/// Returns true for characters of scripts that do not separate words with spaces
+/// (Chinese, Japanese kana, Korean Hangul).
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x11FF     // Hangul Jamo
+        | 0x3005..=0x3007   // Ideographic iteration mark, closing mark and zero
+        | 0x3040..=0x309F   // Hiragana
+        | 0x30A0..=0x30FF   // Katakana
+        | 0x3130..=0x318F   // Hangul Compatibility Jamo
+        | 0x31F0..=0x31FF   // Katakana Phonetic Extensions
+        | 0x3400..=0x4DBF   // CJK Unified Ideographs Extension A
+        | 0x4E00..=0x9FFF   // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF   // Hangul Syllables
+        | 0xF900..=0xFAFF   // CJK Compatibility Ideographs
+        | 0xFF66..=0xFF9F   // Halfwidth Katakana
+        | 0x20000..=0x2EBEF // CJK Unified Ideographs Extensions B to F
+        | 0x2F800..=0x2FA1F // CJK Compatibility Ideographs Supplement
+    )
+}
+
+/// Where a token comes from in the source text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offsets of the token, `start..end`.
+    pub start: usize,
+    pub end: usize,
+    /// 1-based line number.
+    pub line: u32,
+    /// 1-based column, counted in characters.
+    pub col: u32,
+}
+
+/// A slice of the source text with its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl<'a> Token<'a> {
+    // The part of the token at bytes `start..end` of its text, tokens never span lines.
+    fn slice(&self, start: usize, end: usize) -> Token<'a> {
+        Token {
+            text: &self.text[start..end],
+            span: Span {
+                start: self.span.start + start,
+                end: self.span.start + end,
+                line: self.span.line,
+                col: self.span.col + self.text[..start].chars().count() as u32,
+            },
+        }
+    }
+}
+
+// The unread rest of the source, keeping track of the line and column.
 #[derive(Debug)]
-pub struct Lexer<'a>{
-    content: &'a [char],
+struct Cursor<'a> {
+    source: &'a str,
+    offset: usize,
+    line: u32,
+    col: u32,
 }
 
-impl<'a> Lexer<'a> {
-    pub fn new(content: &'a [char]) -> Self {
-        Self {content}
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {source, offset: 0, line: 1, col: 1}
     }
 
-    fn trim_left(&mut self){
-        while self.content.len() > 0 && self.content[0].is_whitespace(){
-            self.content = &self.content[1..];
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    // Takes `n` bytes of the rest as a token.
+    fn chop(&mut self, n: usize) -> Token<'a> {
+        let text = &self.rest()[..n];
+        let span = Span {
+            start: self.offset,
+            end: self.offset + n,
+            line: self.line,
+            col: self.col,
+        };
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
         }
+        self.offset += n;
+        Token {text, span}
     }
-    
-    fn chop_while<P>(&mut self, mut predicate: P) -> &'a[char] where P: FnMut(&char) -> bool{
-             let mut n = 0;
-            // This token begins at an alphabet and ends at non-alphanumeric characters like (*, ^, &,. etc) 
-            while n < self.content.len() && predicate(&self.content[n]){
-                n += 1; // Increment token width
-            } 
-       self.chop(n) // Take the required amount. 
+
+    fn chop_while<P>(&mut self, mut predicate: P) -> Token<'a> where P: FnMut(char) -> bool{
+        // The token ends at the first character that does not match.
+        let n = self.rest().find(|c| !predicate(c)).unwrap_or(self.rest().len());
+        self.chop(n)
     }
-    fn chop(&mut self, n: usize) -> &'a [char]{
- 
-            let token  = &self.content[0..n]; // Get a slice of the current content. 
-            self.content = &self.content[n..]; // The new content excludes the previous token.
 
-            token        
+    fn trim_left(&mut self){
+        self.chop_while(char::is_whitespace);
     }
-    fn next_token(&mut self) -> Option<String>{
-        // trim whitespaces from left.
-        self.trim_left();
+}
 
-        if self.content.len() == 0 {
-            return None
+#[derive(Debug)]
+pub struct Lexer<'a>{
+    cursor: Cursor<'a>,
+    // A run of CJK characters that is being split into bigrams, and the char index of the next one.
+    cjk_run: Option<(Token<'a>, usize)>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {cursor: Cursor::new(source), cjk_run: None}
+    }
+
+    // Emits overlapping bigrams of a CJK run ("中文字" -> "中文", "文字"), a lone character is its own token.
+    fn next_bigram(&mut self) -> Option<Token<'a>>{
+        let (run, index) = self.cjk_run.take()?;
+        let mut chars = run.text.char_indices().skip(index).map(|(i, _)| i);
+        let start = chars.next()?;
+        let end = chars.nth(1).unwrap_or(run.text.len());
+        // Step one character, the last one is already part of the final bigram.
+        if end < run.text.len() {
+            self.cjk_run = Some((run, index + 1));
         }
-        if self.content[0].is_alphabetic(){
-            return Some(self.chop_while(|x| x.is_alphanumeric()).into_iter().map(|x| x.to_ascii_lowercase()).collect::<String>());
-        }else if self.content[0].is_numeric(){
-            return  Some(self.chop_while(|x| x.is_numeric()).into_iter().collect::<String>()); // Chop out the slice we need and return. 
-  
-        }else{
-            return  Some(self.chop(1).into_iter().collect::<String>()); // Chop out the slice we need and return. 
-  
+        Some(run.slice(start, end))
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>>{
+        if let Some(bigram) = self.next_bigram() {
+            return Some(bigram);
         }
 
+        // trim whitespaces from left.
+        self.cursor.trim_left();
+
+        let first = self.cursor.peek()?;
+        if is_cjk(first){
+            // These scripts have no spaces between words, index character bigrams instead.
+            self.cjk_run = Some((self.cursor.chop_while(is_cjk), 0));
+            self.next_bigram()
+        }else if first.is_alphabetic(){
+            Some(self.cursor.chop_while(|x| x.is_alphanumeric() && !is_cjk(x))) // Case folding happens in the analyzer.
+        }else if first.is_numeric(){
+            Some(self.cursor.chop_while(char::is_numeric))
+        }else{
+            Some(self.cursor.chop(first.len_utf8()))
+        }
     }
 }
 
-// Implement the Iteratort trait for the lexer. 
+// Implement the Iteratort trait for the lexer.
 impl<'a> Iterator for Lexer<'a> {
-    type Item = String; 
-    
-    fn next(&mut self) -> Option<String>{
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>>{
         self.next_token()
     }
-}
\ No newline at end of file
+}
+
+// Splits an identifier into its words: `termFrequencyIndex`, `term_frequency_index`
+// and `TERM_FREQUENCY_INDEX` all become `term`, `frequency`, `index` (case is kept).
+// Returns the byte ranges of the words.
+fn split_identifier(identifier: &str) -> Vec<(usize, usize)> {
+    let chars = identifier.char_indices().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, &(offset, cur)) in chars.iter().enumerate() {
+        if cur == '_' {
+            if let Some(start) = start.take() {
+                words.push((start, offset));
+            }
+            continue;
+        }
+        if let Some(word_start) = start {
+            let prev = chars[i - 1].1;
+            let next_is_lower = chars.get(i + 1).is_some_and(|(_, c)| c.is_lowercase());
+            // fooBar, foo2Bar, and HTTPServer -> HTTP Server
+            let boundary = ((prev.is_lowercase() || prev.is_numeric()) && cur.is_uppercase())
+                || (prev.is_uppercase() && cur.is_uppercase() && next_is_lower);
+            if boundary {
+                words.push((word_start, offset));
+                start = Some(offset);
+            }
+        } else {
+            start = Some(offset);
+        }
+    }
+    if let Some(start) = start {
+        words.push((start, identifier.len()));
+    }
+    words
+}
+
+fn is_identifier_start(c: char) -> bool {
+    (c.is_alphabetic() || c == '_') && !is_cjk(c)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    (c.is_alphanumeric() || c == '_') && !is_cjk(c)
+}
+
+// Length in bytes of the identifier at the start of `text`.
+fn identifier_len(text: &str) -> usize {
+    match text.chars().next() {
+        Some(c) if is_identifier_start(c) => {
+            text.find(|c| !is_identifier_char(c)).unwrap_or(text.len())
+        }
+        _ => 0,
+    }
+}
+
+/// A tokenizer for source code.
+///
+/// Yields groups of tokens with their position relative to the group: the whole
+/// identifier or path (`std::collections::HashMap`, `self.index.len`) first, then its
+/// components and their sub-words (`std`, `collections`, `HashMap`, `Hash`, `Map`).
+/// Everything else is tokenized like the prose `Lexer`.
+#[derive(Debug)]
+pub struct CodeLexer<'a>{
+    lexer: Lexer<'a>,
+}
+
+impl<'a> CodeLexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {lexer: Lexer::new(source)}
+    }
+
+    fn next_group(&mut self) -> Option<Vec<(Token<'a>, u32)>>{
+        if self.lexer.cjk_run.is_none() {
+            self.lexer.cursor.trim_left();
+        }
+        let rest = self.lexer.cursor.rest();
+        let first_len = identifier_len(rest);
+        if self.lexer.cjk_run.is_some() || first_len == 0 {
+            // Numbers, symbols and CJK text are split like prose.
+            return self.lexer.next_token().map(|token| vec![(token, 0)]);
+        }
+
+        // Follow `::` and `.` separated paths.
+        let mut components = vec![(0, first_len)];
+        let mut end = first_len;
+        loop {
+            let separator = if rest[end..].starts_with("::") {
+                2
+            } else if rest[end..].starts_with('.') {
+                1
+            } else {
+                break;
+            };
+            let len = identifier_len(&rest[end + separator..]);
+            if len == 0 {
+                break;
+            }
+            components.push((end + separator, end + separator + len));
+            end += separator + len;
+        }
+        let full = self.lexer.cursor.chop(end);
+
+        let mut group = vec![(full, 0)];
+        let mut offset = 0;
+        for (start, end) in components {
+            let component = full.slice(start, end);
+            if component.text != full.text {
+                group.push((component, offset));
+            }
+            let words = split_identifier(component.text);
+            if words.len() > 1 || words.first().is_some_and(|&(s, e)| e - s != component.text.len()) {
+                for (i, (s, e)) in words.iter().enumerate() {
+                    group.push((component.slice(*s, *e), offset + i as u32));
+                }
+            }
+            offset += words.len().max(1) as u32;
+        }
+        Some(group)
+    }
+}
+
+impl<'a> Iterator for CodeLexer<'a> {
+    type Item = Vec<(Token<'a>, u32)>;
+
+    fn next(&mut self) -> Option<Vec<(Token<'a>, u32)>>{
+        self.next_group()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Text, byte offsets, line and column of every token.
+    fn spans(tokens: impl Iterator<Item = Token<'static>>) -> Vec<(&'static str, usize, usize, u32, u32)> {
+        tokens
+            .map(|token| (token.text, token.span.start, token.span.end, token.span.line, token.span.col))
+            .collect()
+    }
+
+    #[test]
+    fn splits_cjk_runs_into_overlapping_bigrams() {
+        assert_eq!(
+            spans(Lexer::new("中文字 ok 中")),
+            vec![
+                ("中文", 0, 6, 1, 1),
+                ("文字", 3, 9, 1, 2),
+                ("ok", 10, 12, 1, 5),
+                ("中", 13, 16, 1, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_identifiers_into_words() {
+        let words = |identifier: &'static str| {
+            split_identifier(identifier)
+                .into_iter()
+                .map(|(start, end)| &identifier[start..end])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words("termFrequencyIndex"), ["term", "Frequency", "Index"]);
+        assert_eq!(words("term_frequency_index"), ["term", "frequency", "index"]);
+        assert_eq!(words("TERM_FREQUENCY_INDEX"), ["TERM", "FREQUENCY", "INDEX"]);
+        assert_eq!(words("HTTPServer"), ["HTTP", "Server"]);
+        assert_eq!(words("foo2Bar"), ["foo2", "Bar"]);
+        assert_eq!(words("__init__"), ["init"]);
+        assert_eq!(words("plain"), ["plain"]);
+    }
+
+    #[test]
+    fn groups_paths_with_their_components_and_words() {
+        let groups = CodeLexer::new("x = self.index_len;")
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|(token, position)| (token.text, token.span.start, token.span.col, position))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                vec![("x", 0, 1, 0)],
+                vec![("=", 2, 3, 0)],
+                vec![
+                    ("self.index_len", 4, 5, 0),
+                    ("self", 4, 5, 0),
+                    ("index_len", 9, 10, 1),
+                    ("index", 9, 10, 1),
+                    ("len", 15, 16, 2),
+                ],
+                vec![(";", 18, 19, 0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_camel_case_after_multibyte_characters() {
+        let group = CodeLexer::new("été → naïveFoo").nth(2).unwrap();
+        let tokens = group
+            .into_iter()
+            .map(|(token, position)| (token.text, token.span.start, token.span.col, position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![("naïveFoo", 10, 7, 0), ("naïve", 10, 7, 0), ("Foo", 16, 12, 1)]
+        );
+    }
+
+    #[test]
+    fn tracks_offsets_lines_and_columns_of_multibyte_text() {
+        assert_eq!(
+            spans(Lexer::new("héllo wörld\n  naïve, 42")),
+            vec![
+                ("héllo", 0, 6, 1, 1),
+                ("wörld", 7, 13, 1, 7),
+                ("naïve", 16, 22, 2, 3),
+                (",", 22, 23, 2, 8),
+                ("42", 24, 26, 2, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn columns_count_characters_not_bytes() {
+        let tokens = spans(Lexer::new("😀 ok"));
+        assert_eq!(tokens, vec![("😀", 0, 4, 1, 1), ("ok", 5, 7, 1, 3)]);
+    }
+}
@@ -0,0 +1,50 @@
+//! Seroost indexes documents and source code and ranks them with TF-IDF.
+//!
+//! The `seroost` binary is built on this library. `Indexer` builds an `Index` of a
+//! directory, `Searcher` answers a `Query` against it with ranked `SearchHit`s. None
+//! of them print anything, what happens to each file is reported to a callback.
+//!
+//! ```no_run
+//! use seroost::config::Config;
+//! use seroost::registry::ParserRegistry;
+//! use seroost::{Indexer, Searcher};
+//! use std::sync::Arc;
+//!
+//! let collection = Config::default().collection();
+//! let registry = Arc::new(ParserRegistry::from_config(&collection)?);
+//! let (index, report) = Indexer::new(registry, collection.analysis.clone())
+//!     .on_progress(Arc::new(|progress| eprintln!("{progress:?}")))
+//!     .index_dir("/path/to/documents")?;
+//! println!("{} documents, {} files skipped", report.indexed, report.skipped.len());
+//!
+//! let searcher = Searcher::new(index).with_boosts(collection.boosts);
+//! let query = searcher.parse("def:parse tokenizer");
+//! for hit in searcher.search(&query, 10, 0).hits {
+//!     println!("{} {:.5}", hit.path.display(), hit.score);
+//! }
+//! # Ok::<(), seroost::Error>(())
+//! ```
+
+pub mod analysis;
+pub mod config;
+pub mod encoding;
+pub mod error;
+pub mod index;
+pub mod indexer;
+pub mod languages;
+pub mod lexer;
+pub mod parsers;
+pub mod query;
+pub mod regions;
+pub mod registry;
+pub mod report;
+pub mod searcher;
+pub mod sniff;
+pub mod stopwords;
+pub mod symbols;
+
+pub use error::Error;
+pub use index::{Index, SearchHit};
+pub use indexer::Indexer;
+pub use query::Query;
+pub use searcher::{SearchResults, Searcher};
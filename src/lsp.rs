@@ -0,0 +1,304 @@
+use crate::daemon::SearchRequest;
+use crate::percent;
+use crate::rpc::{self, Message, Output, RpcError, Session};
+use seroost::config::Config;
+use seroost::index;
+use seroost::indexer::{Progress, SkipReason};
+use seroost::Error;
+use seroost::registry::ParserRegistry;
+use seroost::symbols::SymbolKind;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Error code of the Language Server Protocol for requests sent before `initialize`.
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// How many documents `workspace/symbol` takes definitions from.
+const SYMBOL_DOCUMENTS: usize = 100;
+
+// Message types of `window/logMessage`.
+const LOG_WARNING: u8 = 2;
+const LOG_INFO: u8 = 3;
+
+/// Decodes the path of a `file://` URI.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    Some(PathBuf::from(percent::decode(path, false)))
+}
+
+/// Encodes a path as a `file://` URI, escaping everything but unreserved characters and `/`.
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+/// The `SymbolKind` number of the protocol.
+fn symbol_kind(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Module => 2,
+        SymbolKind::Class => 5,
+        SymbolKind::Method => 6,
+        SymbolKind::Enum => 10,
+        SymbolKind::Interface => 11,
+        SymbolKind::Function => 12,
+        SymbolKind::Constant => 14,
+        SymbolKind::Struct => 23,
+        SymbolKind::Type => 26,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeParams {
+    root_uri: Option<String>,
+    root_path: Option<String>,
+    #[serde(default)]
+    workspace_folders: Option<Vec<WorkspaceFolder>>,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceFolder {
+    uri: String,
+}
+
+impl InitializeParams {
+    /// The workspace root: the first folder, the root URI or the deprecated root path.
+    fn root(&self) -> Option<PathBuf> {
+        let folder = self.workspace_folders.iter().flatten().next();
+        folder
+            .map(|folder| folder.uri.as_str())
+            .or(self.root_uri.as_deref())
+            .and_then(uri_to_path)
+            .or_else(|| self.root_path.as_ref().map(PathBuf::from))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidSaveParams {
+    text_document: TextDocument,
+}
+
+#[derive(Deserialize)]
+struct TextDocument {
+    uri: String,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceSymbolParams {
+    query: String,
+}
+
+/// A language server answering from the seroost index of the workspace root.
+struct Server {
+    configuration: Config,
+    max_file_size: u64,
+    output: Output,
+    session: Option<Session>,
+}
+
+impl Server {
+    fn log(&self, kind: u8, message: impl Into<String>) {
+        let message: String = message.into();
+        self.output.notify("window/logMessage", json!({ "type": kind, "message": message }));
+    }
+
+    fn session(&mut self) -> Result<&mut Session, RpcError> {
+        self.session
+            .as_mut()
+            .ok_or_else(|| RpcError::new(SERVER_NOT_INITIALIZED, "the server is not initialized"))
+    }
+
+    /// Opens a session on the workspace root with an index of its own, the saved
+    /// configuration and the index of the CLI are left alone.
+    fn initialize(&mut self, params: Value) -> Result<Value, RpcError> {
+        let params: InitializeParams = rpc::params(params)?;
+        let root = match params.root() {
+            Some(root) => root,
+            None => std::env::current_dir().map_err(|e| RpcError::new(rpc::INTERNAL_ERROR, e.to_string()))?,
+        };
+        let root = root.to_string_lossy().into_owned();
+
+        let collection = self.configuration.collection_at(&root);
+        let registry = ParserRegistry::from_config(&collection)
+            .map_err(|e| RpcError::new(rpc::INTERNAL_ERROR, e.to_string()))?;
+        let registry = Arc::new(registry);
+        let index_path = index::workspace_index_path(&root);
+        let mut session = Session::new(root, self.max_file_size, collection, registry);
+        session.output = self.output.clone();
+        session.index_path = index_path;
+        self.session = Some(session);
+
+        Ok(json!({
+            "capabilities": {
+                "workspaceSymbolProvider": true,
+                "textDocumentSync": { "openClose": false, "change": 0, "save": { "includeText": false } },
+            },
+            "serverInfo": { "name": "seroost", "version": env!("CARGO_PKG_VERSION") },
+        }))
+    }
+
+    /// Indexes the workspace when it has no index yet, logging the skipped files.
+    fn initialized(&mut self) -> Result<(), RpcError> {
+        let output = self.output.clone();
+        let session = self.session()?;
+        let missing = session.searcher().is_err_and(|e| e.code == rpc::INDEX_NOT_FOUND);
+        if !missing {
+            return Ok(());
+        }
+
+        let log = output.clone();
+        let report = Arc::new(move |progress: Progress| {
+            let (kind, message) = match progress {
+                Progress::Indexed { .. } => return,
+                Progress::Skipped { path, reason } => {
+                    let kind = match reason {
+                        SkipReason::Unreadable(_) | SkipReason::ParseError { .. } => LOG_WARNING,
+                        _ => LOG_INFO,
+                    };
+                    (kind, format!("Skipping {path}: {reason}"))
+                }
+                Progress::Warning { path, message } => (LOG_WARNING, format!("{path}: {message}")),
+            };
+            log.notify("window/logMessage", json!({ "type": kind, "message": message }));
+        });
+        let documents = session.build(report)?.indexed;
+        let root = session.root.clone();
+        self.log(LOG_INFO, format!("Indexed {documents} documents in {root}"));
+        Ok(())
+    }
+
+    /// `workspace/symbol`: definitions matching every word of the query, exact names first.
+    fn workspace_symbol(&mut self, params: Value) -> Result<Value, RpcError> {
+        let params: WorkspaceSymbolParams = rpc::params(params)?;
+        if params.query.trim().is_empty() {
+            return Ok(json!([]));
+        }
+        let request = SearchRequest {
+            query: params.query,
+            symbol: true,
+            limit: SYMBOL_DOCUMENTS,
+            offset: 0,
+        };
+        let session = self.session()?;
+        let searcher = session.searcher()?;
+        // A query of punctuation only has no terms, it finds nothing rather than failing.
+        let Ok(response) = request.run(searcher) else {
+            return Ok(json!([]));
+        };
+
+        let symbols = response
+            .results
+            .iter()
+            .flat_map(|hit| {
+                let uri = path_to_uri(&hit.path);
+                let container = hit.path.file_name().map(|name| name.to_string_lossy().into_owned());
+                hit.definitions.iter().map(move |symbol| {
+                    let position = json!({ "line": symbol.line.saturating_sub(1), "character": 0 });
+                    json!({
+                        "name": symbol.name,
+                        "kind": symbol_kind(symbol.kind),
+                        "location": { "uri": uri, "range": { "start": position, "end": position } },
+                        "containerName": container,
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(Value::Array(symbols))
+    }
+
+    /// `textDocument/didSave`: indexes the saved file again.
+    fn did_save(&mut self, params: Value) -> Result<(), RpcError> {
+        let params: DidSaveParams = rpc::params(params)?;
+        let path = uri_to_path(&params.text_document.uri)
+            .ok_or_else(|| RpcError::new(rpc::INVALID_PARAMS, format!("not a file URI: {}", params.text_document.uri)))?;
+        let result = self.session()?.reindex_file(&path)?;
+        if let Some(reason) = result.get("reason").and_then(Value::as_str) {
+            self.log(LOG_WARNING, format!("Could not index {}: {reason}", path.display()));
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => self.initialize(params),
+            "shutdown" => Ok(Value::Null),
+            "workspace/symbol" => self.workspace_symbol(params),
+            // The same parameters and results as the `search` method of `seroost rpc`.
+            "seroost/search" => self.session()?.search(params),
+            _ => Err(RpcError::new(rpc::METHOD_NOT_FOUND, format!("unknown method `{method}`"))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: Value) {
+        let result = match method {
+            "initialized" => self.initialized(),
+            "textDocument/didSave" => self.did_save(params),
+            // Other notifications, like `$/cancelRequest`, need no answer.
+            _ => Ok(()),
+        };
+        if let Err(error) = result {
+            self.log(LOG_WARNING, format!("{method}: {}", error.message));
+        }
+    }
+}
+
+/// Runs a minimal language server on stdin and stdout until the client sends `exit`.
+pub fn run(configuration: Config, max_file_size: u64) -> Result<(), Error> {
+    let mut server = Server {
+        configuration,
+        max_file_size,
+        output: Output::default(),
+        session: None,
+    };
+    let mut input = io::stdin().lock();
+    while let Some(text) = server.output.receive(&mut input) {
+        let message = match Message::parse(&text?) {
+            Ok(message) => message,
+            Err(error) => {
+                server.output.respond(Value::Null, Err(error));
+                continue;
+            }
+        };
+        match message.id {
+            Some(id) => {
+                let result = server.request(&message.method, message.params);
+                server.output.respond(id, result);
+            }
+            None if message.method == "exit" => break,
+            None => server.notification(&message.method, message.params),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_file_uris() {
+        assert_eq!(uri_to_path("file:///home/me/my%20notes/caf%C3%A9.md"), Some(PathBuf::from("/home/me/my notes/café.md")));
+        // A `%` without two hex digits is kept.
+        assert_eq!(uri_to_path("file:///tmp/100%/a%2"), Some(PathBuf::from("/tmp/100%/a%2")));
+        assert_eq!(uri_to_path("file:///tmp/%+1"), Some(PathBuf::from("/tmp/%+1")));
+        assert_eq!(uri_to_path("https://example.com/a"), None);
+    }
+
+    #[test]
+    fn uris_round_trip_through_paths() {
+        let path = Path::new("/tmp/we ird/ünï#code?.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///tmp/we%20ird/%C3%BCn%C3%AF%23code%3F.rs");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+    }
+}
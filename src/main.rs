@@ -1,14 +1,17 @@
 use clap::{Parser, Subcommand};
 use colored::*; // Add this import
-use serde_json;
-use std::collections::HashMap;
-use std::fs; // Get the file system.
-use std::io;
-use std::process;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use seroost::{config, registry, Error};
 
 // Import Modules.
-mod lexer;
-mod parsers;
+mod daemon;
+mod lsp;
+mod percent;
+mod rpc;
+mod server;
+mod tui;
 mod interact;
 mod interactives; 
 // Define CLI Interface.
@@ -44,16 +47,82 @@ enum AppCommands {
 
     /// Searches the Indexed documents for a document matching your description.
     Search {
-        /// Term to search for
+        /// Term to search for.
+        /// Restrict a word to a field with `title:word`, boost a field with `title^3`
+        /// and match an exact phrase with quotes.
+        /// Look for the definition of a symbol with `def:name`.
         #[arg(required = true)]
         term: String,
+
+        /// Look for definitions of every word, like writing `def:` before each one.
+        #[arg(long)]
+        symbol: bool,
+
+        /// Output format, defaults to the output mode.
+        /// vimgrep prints `path:line:col:text`, ndjson one JSON object per result,
+        /// csv a row per result and paths only the paths.
+        #[arg(long, value_enum)]
+        format: Option<interact::Format>,
+
+        /// Separate paths with NUL instead of newlines, for `xargs -0`. Implies `--format paths`.
+        #[arg(short = '0', long)]
+        null: bool,
+
+        /// Number of results to show.
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
     },
 
     /// Displays detailed usage instructions and examples
     Usage,
+
+    /// Lists the supported file formats and the extensions they are picked by.
+    Formats,
+
+    /// Shows the files the last `index` left out, with counts per reason and extension.
+    Report {
+        /// Print the report as JSON, for CI.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Loads the index once and answers searches over HTTP with JSON.
+    Serve {
+        /// Port to listen on.
+        #[arg(short, long, default_value = "6969")]
+        port: u16,
+        /// Address to listen on, `0.0.0.0` serves other machines too.
+        #[arg(long, alias = "bind", default_value = "127.0.0.1")]
+        host: String,
+    },
+
+    /// Keeps the index in memory and answers searches on a Unix socket.
+    /// `search` uses it while it runs.
+    Daemon,
+
+    /// Answers JSON-RPC 2.0 requests on stdin and stdout, for editor integrations.
+    /// Methods: search, index, reindexFile and stats.
+    Rpc,
+
+    /// Runs a minimal language server for the workspace root sent by the editor,
+    /// answering workspace symbol and `seroost/search` requests.
+    Lsp,
+
+    /// Searches as you type in a full-screen terminal interface with a preview pane.
+    Tui,
 }
 
-fn main() -> Result<(), parsers::GlobalError> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{} {err}", "Error:".red());
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<(), Error> {
     // Parse CLI args.
     let cli = Cli::parse();
     // Create Index Path Variable to  be filled dynamically. 
@@ -64,71 +133,93 @@ fn main() -> Result<(), parsers::GlobalError> {
     let output_mode = match cli.mode.as_deref() {
         Some("regular") => interact::Mode::Regular,
         Some("code") => interact::Mode::Code,
-        _ => {
-            eprintln!("{}", format!("Error: Invalid Output mode, expected one of: [regular, code]").color("red")); 
-            process::exit(1);
-        }
+        _ => return Err(Error::Config("Invalid Output mode, expected one of: [regular, code]".to_string())),
     };
 
-    // See if an index path was provided.
-    if let Some(path) = &cli.index_path {
-        let config_path = interact::get_config_path(); // Use the same config path regardless
+    // Load the saved configuration; a broken file is reported, never replaced.
+    let mut configuration = config::Config::load()?;
 
-        // Create parent directories if needed
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
+    // Listing the formats only needs the collection settings, not an index path.
+    if let (Some(AppCommands::Formats), None) = (&cli.command, &cli.index_path) {
+        let registry = registry::ParserRegistry::from_config(&configuration.collection())?;
+        interact::display_formats(&registry);
+        return Ok(());
+    }
 
-        // Load existing config or create new one
-        let mut configuration = if config_path.exists() {
-            let file = fs::File::open(&config_path)?;
-            // Return the parsed Json File as a HashMap
-            serde_json::from_reader(file).unwrap_or_else(|_| HashMap::<String, String>::new())
-        } else {
-            HashMap::<String, String>::new()
-        };
+    // The report of the last run is next to the index, it needs no index path.
+    if let Some(AppCommands::Report { json }) = &cli.command {
+        return interact::display_report(*json);
+    }
 
-        // Update configuration
-        configuration.insert("index_path".to_string(), path.clone());
+    // The language server indexes the workspace root the editor sends.
+    if let Some(AppCommands::Lsp) = &cli.command {
+        return lsp::run(configuration, max_file_size);
+    }
 
-        // Write back to file
-        let file = fs::File::create(&config_path)?;
-        serde_json::to_writer(file, &configuration)?;
+    // See if an index path was provided.
+    if let Some(path) = &cli.index_path {
+        // Update configuration and write it back to file
+        configuration.index_path = Some(path.clone());
+        configuration.save()?;
 
         // Use in program memory
         index_path = path.to_string();
+    } else if let Some(path) = &configuration.index_path {
+        index_path = path.clone();
+    } else if !config::get_config_path().exists() {
+        return Err(Error::Config(
+            "No index path provided and no saved configuration found. Please run the program with --index-path option first:\n    seroost --index-path /path/to/documents index"
+                .to_string(),
+        ));
     } else {
-        // Use the SAME config path for reading
-        let config_path = interact::get_config_path();
-
-        if !config_path.exists() {
-            eprintln!("{}", format!("Error: No index path provided and no saved configuration found.").color("red"));
-            eprintln!("{}", format!("Please run the program with --index-path option first:").color("red"));
-            eprintln!("{}", format!("    seroost  --index-path /path/to/documents index").color("green"));
-            process::exit(0);
-        }
-        let file = fs::File::open(config_path)?;
-        let reader = std::io::BufReader::new(file);
-
-        // Read the JSON contents of the file.
-        let config: HashMap<String, String> =
-            serde_json::from_reader(reader).expect("Error Parsing The Json configuration file");
-        index_path = config
-            .get("index_path")
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No index_path in config file"))?
-            .clone();
+        return Err(Error::Config("No index_path in config file".to_string()));
     }
+    let collection = configuration.collection();
+    let registry = Arc::new(registry::ParserRegistry::from_config(&collection)?);
 
     match &cli.command {
         Some(AppCommands::Index) => {
-            interactives::process_file(index_path, max_file_size);
+            interactives::process_file(
+                index_path,
+                max_file_size,
+                registry,
+                collection.analysis.clone(),
+            )?;
         }
-        Some(AppCommands::Search { term }) => {
-            interact::search_documents(term, output_mode)?;
+        Some(AppCommands::Search {
+            term,
+            symbol,
+            format,
+            null,
+            limit,
+        }) => {
+            let format = match (format, null) {
+                (Some(format), _) => *format,
+                (None, true) => interact::Format::Paths,
+                (None, false) => output_mode.into(),
+            };
+            interact::search_documents(term, *symbol, *limit, format, *null, &collection, &registry)?;
         }
         Some(AppCommands::Usage) => {
             interact::display_usage()?;
         }
+        Some(AppCommands::Formats) => {
+            interact::display_formats(&registry);
+        }
+        Some(AppCommands::Serve { port, host }) => {
+            server::serve(host, *port, &collection, &registry)?;
+        }
+        Some(AppCommands::Daemon) => {
+            daemon::run(collection)?;
+        }
+        Some(AppCommands::Rpc) => {
+            rpc::run(index_path, max_file_size, collection, registry)?;
+        }
+        Some(AppCommands::Tui) => {
+            tui::run(&collection, &registry)?;
+        }
+        // Answered before an index path is needed.
+        Some(AppCommands::Report { .. } | AppCommands::Lsp) => unreachable!("handled before the index path is read"),
         None => {
             println!("{}. Use --help for usage information.", "No command provided".red());
             println!("Or try: {} for detailed examples", "seroost usage".green());
@@ -137,8 +228,3 @@ fn main() -> Result<(), parsers::GlobalError> {
 
     Ok(())
 }
-
-
-
-
-
@@ -1,17 +1,183 @@
+use crate::analysis::Tokenizer;
+use crate::encoding;
+use crate::error::Error;
+use crate::languages::Language;
+use crate::regions::{self, Region};
+use crate::symbols::{self, Symbol};
 use lopdf::Document;
 use select::document::Document as HtmlDocument;
 use select::predicate::{Name, Predicate, Text};
-use std::error::Error;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs; // Get the file system.
 use std::io;
-use std::io::{BufReader, Read}; // Get the io module.
+use std::io::Read; // Get the io module.
+use std::ops::Range;
 use std::path::Path;
-use std::process::exit; // get the process's exit function.
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
 use xml::reader::{EventReader, XmlEvent};
 
-pub type GlobalError = Box<dyn Error>;
+/// The parts of a document that are indexed separately so they can be boosted independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Title,
+    Headings,
+    Body,
+    Path,
+    Symbols,
+    /// Code files are indexed by region instead of as a single body.
+    Code,
+    Comment,
+    String,
+}
+
+impl Field {
+    pub const ALL: [Field; 8] = [
+        Field::Title,
+        Field::Headings,
+        Field::Body,
+        Field::Path,
+        Field::Symbols,
+        Field::Code,
+        Field::Comment,
+        Field::String,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Headings => "headings",
+            Field::Body => "body",
+            Field::Path => "path",
+            Field::Symbols => "symbols",
+            Field::Code => "code",
+            Field::Comment => "comment",
+            Field::String => "string",
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Field::ALL
+            .into_iter()
+            .find(|field| field.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown field `{name}`"))
+    }
+}
+
+/// The structured output of every parser.
+#[derive(Debug, Default, Clone)]
+pub struct ParsedDocument {
+    pub path: String,
+    pub title: Option<String>,
+    pub headings: Vec<String>,
+    pub body: String,
+    pub symbols: Vec<Symbol>,
+    /// Code, comment and string regions of the body of a code file, empty for other documents.
+    pub regions: Vec<(Region, Range<usize>)>,
+    /// How the fields are tokenized, set from the parser that produced the document.
+    pub tokenizer: Tokenizer,
+    /// Problems that did not stop the parser, like bytes of no known encoding.
+    pub warnings: Vec<String>,
+}
+
+impl ParsedDocument {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_string_lossy().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Makes the indexed path relative to the root of the collection: the words of the root
+    /// are in every document, they would match everything and rank nothing.
+    pub fn strip_root<P: AsRef<Path>>(&mut self, root: P) {
+        if let Ok(relative) = Path::new(&self.path).strip_prefix(root) {
+            self.path = relative.to_string_lossy().into_owned();
+        }
+    }
+
+    /// Returns the text of every non-empty field.
+    /// The body of a code file is split into its code, comment and string regions.
+    pub fn fields(&self) -> Vec<(Field, String)> {
+        let mut fields = vec![
+            (Field::Title, self.title.clone().unwrap_or_default()),
+            (Field::Headings, self.headings.join("\n")),
+            (Field::Path, self.path.clone()),
+            // One symbol per line, the line of a match tells which symbol it is.
+            (
+                Field::Symbols,
+                self.symbols
+                    .iter()
+                    .map(|symbol| symbol.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        ];
+        if self.regions.is_empty() {
+            fields.push((Field::Body, self.body.clone()));
+        } else {
+            for region in Region::ALL {
+                fields.push((region.field(), regions::mask(&self.body, &self.regions, region)));
+            }
+        }
+        fields.retain(|(_, text)| !text.trim().is_empty());
+        fields
+    }
+}
+
+// Returns the first non-empty line of a text, used as a fallback title.
+fn first_line(text: &str) -> Option<String> {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Reads a text file in whatever encoding it uses, see `encoding::decode`.
+/// Falls back to lossy UTF-8 when the encoding cannot be determined.
+pub fn read_text_file<P: AsRef<Path>>(path: P) -> Result<String, io::Error> {
+    Ok(encoding::read_file(&path)?.text)
+}
 
-pub fn read_entire_pdf_file<P: AsRef<Path>>(file_path: P) -> Result<String, GlobalError> {
+// Reads the text of a document, noting in its warnings when bytes had to be replaced.
+fn read_document_text<P: AsRef<Path>>(parsed: &mut ParsedDocument, path: P) -> Result<String, io::Error> {
+    let decoded = encoding::read_file(&path)?;
+    if decoded.lossy {
+        parsed.warnings.push(format!(
+            "could not determine the text encoding, invalid {} bytes were replaced",
+            decoded.encoding
+        ));
+    }
+    Ok(decoded.text)
+}
+
+// Reads the `/Title` entry of the PDF's document information dictionary.
+fn pdf_title(doc: &Document) -> Option<String> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    let (_, info) = doc.dereference(info).ok()?;
+    let title = info.as_dict().ok()?.get(b"Title").ok()?;
+    let (_, title) = doc.dereference(title).ok()?;
+    let title = lopdf::decode_text_string(title).ok()?;
+    let title = title.trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+pub fn read_entire_pdf_file<P: AsRef<Path>>(file_path: P) -> Result<ParsedDocument, Error> {
+    let mut parsed = ParsedDocument::new(&file_path);
     // Load pdf-file
     let doc = Document::load(file_path)?;
     // get the number of pages.
@@ -20,90 +186,100 @@ pub fn read_entire_pdf_file<P: AsRef<Path>>(file_path: P) -> Result<String, Glob
     const MAX_PAGES: usize = 450;
     // create a string for the page content;
     let mut page_content: String = String::new();
-    if pages < MAX_PAGES {
-        // move page by page.
-        for page in 1..=pages {
-            // Build an all lower-case string from the text.
-            page_content.push_str(
-                &*doc.extract_text(&[page as u32])?. // Note that &* here converts String to &str
-            chars()
-            .map(|character| {
-                if character.is_alphabetic(){
-                    return character.to_ascii_lowercase();
-                }
-                character
-            }).collect::<String>(),
-            );
-        }
-    } else {
-        for page in 1..=MAX_PAGES {
-            // Build an all lower-case string from the text.
-            page_content.push_str(
-                &*doc.extract_text(&[page as u32])?. // Note that &* here converts String to &str
-            chars()
-            .map(|character| {
-                if character.is_alphabetic(){
-                    return character.to_ascii_lowercase();
-                }
-                character
-            }).collect::<String>(),
-            );
-        }
+    // move page by page.
+    for page in 1..=pages.min(MAX_PAGES) {
+        page_content.push_str(&doc.extract_text(&[page as u32])?);
+    }
+
+    // The outline (bookmarks) doubles as the document's headings.
+    if let Ok(toc) = doc.get_toc() {
+        parsed.headings = toc.toc.into_iter().map(|entry| entry.title).collect();
     }
+    parsed.title = pdf_title(&doc).or_else(|| first_line(&page_content));
+    parsed.body = page_content;
 
     // Return the file content.
-    Ok(page_content)
+    Ok(parsed)
 }
 
-pub fn read_entire_txt_file<P: AsRef<Path>>(file_path: P) -> Result<String, GlobalError> {
-    let file = fs::File::open(file_path)?;
-    let mut buf_reader = BufReader::new(file);
-    let mut contents = String::new();
-    buf_reader.read_to_string(&mut contents)?;
-
-    let contents = contents
-        .chars()
-        .map(|character| {
-            if character.is_alphabetic() {
-                return character.to_ascii_lowercase();
-            }
-            character
-        })
-        .collect::<String>();
+pub fn read_entire_txt_file<P: AsRef<Path>>(file_path: P) -> Result<ParsedDocument, Error> {
+    let mut parsed = ParsedDocument::new(&file_path);
+    let contents = read_document_text(&mut parsed, file_path)?;
+
+    parsed.title = first_line(&contents);
+    parsed.body = contents;
 
-    Ok(contents)
+    Ok(parsed)
 }
 
-pub fn read_entire_xml_file<P: AsRef<Path>>(file_path: P) -> Result<String, GlobalError> {
+pub fn read_entire_xml_file<P: AsRef<Path>>(file_path: P) -> Result<ParsedDocument, Error> {
+    let mut parsed = ParsedDocument::new(&file_path);
     let file = fs::File::open(file_path)?;
 
     // Create an XML event reader.
     let er = EventReader::new(file);
     // Create a buffer.
     let mut content = String::new();
+    // Depth inside a <title> element, its text becomes the document title.
+    let mut title_depth = 0usize;
+    let mut title = String::new();
     // Create an Iterator and map it.
     for event in er.into_iter() {
-        if let XmlEvent::Characters(text) = event.unwrap_or_else(|err| {
-            // Report the error.
-            eprintln!("ERROR: {err}");
-            exit(1); // Exit the process with an error code of 1
-        }) {
-            // Convert to lowercase and add to the content.
-            content.push_str(&text.to_ascii_lowercase());
-            content.push_str(" "); // For Padding.
+        // A malformed document fails on its own, the rest of the run goes on.
+        match event? {
+            XmlEvent::StartElement { name, .. }
+                if name.local_name.eq_ignore_ascii_case("title") && parsed.title.is_none() =>
+            {
+                title_depth += 1;
+            }
+            XmlEvent::EndElement { name }
+                if title_depth > 0 && name.local_name.eq_ignore_ascii_case("title") =>
+            {
+                title_depth -= 1;
+                if title_depth == 0 {
+                    parsed.title = first_line(&title);
+                }
+            }
+            XmlEvent::Characters(text) => {
+                if title_depth > 0 {
+                    title.push_str(&text);
+                }
+                content.push_str(&text);
+                content.push(' '); // For Padding.
+            }
+            _ => {}
         }
     }
+    parsed.body = content;
 
     // Return the file content.
-    Ok(content)
+    Ok(parsed)
 }
 
-pub fn read_entire_html_file<P: AsRef<Path>>(path: P) -> Result<String, io::Error> {
-    let html_content = std::fs::read_to_string(path)?;
+pub fn read_entire_html_file<P: AsRef<Path>>(path: P) -> Result<ParsedDocument, io::Error> {
+    let mut parsed = ParsedDocument::new(&path);
+    let html_content = read_document_text(&mut parsed, path)?;
 
     // Parse the HTML
     let document = HtmlDocument::from(html_content.as_str());
 
+    parsed.title = document
+        .find(Name("title"))
+        .next()
+        .and_then(|node| first_line(&node.text()));
+    parsed.headings = document
+        .find(
+            Name("h1")
+                .or(Name("h2"))
+                .or(Name("h3"))
+                .or(Name("h4"))
+                .or(Name("h5"))
+                .or(Name("h6")),
+        )
+        .map(|node| node.text().trim().to_string())
+        .filter(|heading| !heading.is_empty())
+        .collect();
+
     // Extract meaningful text (ignoring scripts, styles, etc.)
     let mut text = String::new();
 
@@ -112,38 +288,89 @@ pub fn read_entire_html_file<P: AsRef<Path>>(path: P) -> Result<String, io::Erro
         text.push_str(&node.text());
         text.push(' ');
     }
+    parsed.body = text;
 
-    Ok(text)
+    Ok(parsed)
 }
 
+/// Runs an external extractor such as `djvutxt {path} -` and uses its stdout as the document text.
+///
+/// The command is split on whitespace and `{path}` is replaced in every argument, no shell is involved.
+/// The process is killed when it runs longer than `timeout`.
+pub fn read_with_external_command<P: AsRef<Path>>(
+    path: P,
+    command: &str,
+    timeout: Duration,
+) -> Result<ParsedDocument, Error> {
+    let mut parsed = ParsedDocument::new(&path);
+    let path_str = path.as_ref().to_string_lossy();
+    let mut args = command
+        .split_whitespace()
+        .map(|arg| arg.replace("{path}", &path_str));
+    let program = args.next().ok_or_else(|| Error::Config("empty extractor command".to_string()))?;
 
+    let mut child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| Error::Parse(format!("could not run `{program}`: {err}")))?;
 
-pub fn read_code_file<P: AsRef<Path>>(path: P) -> Result<String, io::Error> {
-    let code_content = std::fs::read_to_string(&path)?;
-    
-    // Add line numbers to each line for better search results
-    let mut numbered_content = String::new();
-    
-    for (line_number, line) in code_content.lines().enumerate() {
-        // Format: "Line X: content"
-        numbered_content.push_str(&format!("Line {}: {}\n", line_number + 1, line));
-    }
-    
-    Ok(numbered_content)
-}
-
-// function specifically for getting line information
-pub fn get_code_line_info<P: AsRef<Path>>(path: P, search_term: &str) -> Result<Vec<(usize, String)>, io::Error> {
-    let code_content = std::fs::read_to_string(&path)?;
-    let mut matches = Vec::new();
-    
-    for (line_number, line) in code_content.lines().enumerate() {
-        if line.to_lowercase().contains(&search_term.to_lowercase()) {
-            matches.push((line_number + 1, line.to_string()));
+    // Drain both pipes on their own threads so a chatty extractor cannot block on a full pipe.
+    let mut stdout = child.stdout.take().ok_or_else(|| io::Error::other("extractor stdout unavailable"))?;
+    let mut stderr = child.stderr.take().ok_or_else(|| io::Error::other("extractor stderr unavailable"))?;
+    let stdout_handle = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let stderr_handle = thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stderr.read_to_end(&mut output);
+        output
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
         }
+        if started.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Error::Parse(format!("`{program}` timed out after {}s", timeout.as_secs())));
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    let output = stdout_handle
+        .join()
+        .map_err(|_| io::Error::other("extractor stdout reader panicked"))??;
+    let errors = stderr_handle.join().unwrap_or_default();
+    if !status.success() {
+        let errors = String::from_utf8_lossy(&errors);
+        let reason = errors.lines().next().unwrap_or("no error output");
+        return Err(Error::Parse(format!("`{program}` exited with {status}: {reason}")));
     }
-    
-    Ok(matches)
+
+    let text = String::from_utf8_lossy(&output);
+    parsed.title = first_line(&text);
+    parsed.body = text.into_owned();
+
+    Ok(parsed)
 }
 
+/// Reads a code file, the language decides how symbols and regions are found.
+pub fn read_code_file<P: AsRef<Path>>(path: P, language: Option<&Language>) -> Result<ParsedDocument, io::Error> {
+    let mut parsed = ParsedDocument::new(&path);
+    let code_content = read_document_text(&mut parsed, &path)?;
 
+    // Line numbers of matches come from the term positions stored in the index.
+    parsed.symbols = symbols::extract(&code_content, language);
+    if let Some(language) = language {
+        parsed.regions = regions::classify(&code_content, &language.syntax);
+    }
+    parsed.body = code_content;
+
+    Ok(parsed)
+}
@@ -0,0 +1,39 @@
+/// Decodes the `%XX` escapes of a URL component or a `file://` URI. A `%` that is not
+/// followed by two hex digits is kept as it is; with `plus_as_space`, as in the query
+/// string of a form, a `+` is a space.
+pub fn decode(text: &str, plus_as_space: bool) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1..i + 3)) {
+            (b'%', Some(&[high, low])) if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                decoded.push(hex_value(high) << 4 | hex_value(low));
+                i += 3;
+                continue;
+            }
+            (b'+', _) if plus_as_space => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex_value(digit: u8) -> u8 {
+    (digit as char).to_digit(16).unwrap_or_default() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_escapes_of_two_hex_digits_only() {
+        assert_eq!(decode("caf%C3%A9%20%e4%b8%ad", false), "café 中");
+        assert_eq!(decode("%+1%-1%1", false), "%+1%-1%1");
+        assert_eq!(decode("100%%41%zz%4", false), "100%A%zz%4");
+        assert_eq!(decode("a+b%2B", false), "a+b+");
+        assert_eq!(decode("a+b%2B", true), "a b+");
+    }
+}
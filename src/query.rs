@@ -0,0 +1,198 @@
+use crate::analysis::{Analyzer, Term, Tokenizer};
+use crate::lexer;
+use crate::parsers::Field;
+use std::collections::HashMap;
+
+/// A search term or a quoted phrase, optionally restricted to one field (`title:rust`).
+#[derive(Debug, Clone)]
+pub struct Clause {
+    pub field: Option<Field>,
+    /// Written as `def:word`, documents defining a matching symbol rank above mere mentions.
+    pub definition: bool,
+    /// The analyzed terms with positions relative to the first one, a single term unless the clause is a phrase.
+    pub terms: Vec<Term>,
+}
+
+/// A parsed search query.
+///
+/// Words of the form `field:word` only match inside that field, words of the
+/// form `field^weight` set the boost of a field for this query only and
+/// `"quoted words"` only match as a phrase. Words in CJK scripts are matched
+/// as a phrase of their character bigrams. `def:word` ranks the documents
+/// defining a symbol of that name first.
+#[derive(Debug, Default, Clone)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+    pub boosts: HashMap<Field, f64>,
+}
+
+// Splits a query on whitespace, keeping quoted phrases (`title:"foo bar"`) in one piece.
+fn split_words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+impl Query {
+    /// Parses a query, every word goes through the same analysis as the indexed documents.
+    ///
+    /// Stop words are dropped like in prose, unless the query has nothing else: then it
+    /// looks for them in code, which keeps them (`for`, `def:all`).
+    pub fn parse(input: &str, analyzer: &Analyzer) -> Self {
+        let query = Self::parse_with(input, analyzer, true);
+        if query.is_empty() {
+            return Self::parse_with(input, analyzer, false);
+        }
+        query
+    }
+
+    fn parse_with(input: &str, analyzer: &Analyzer, drop_stop_words: bool) -> Self {
+        let mut query = Query::default();
+
+        for word in split_words(input) {
+            // Query-time boost, e.g. `title^3`.
+            if let Some((name, weight)) = word.split_once('^') {
+                if let (Ok(field), Ok(weight)) = (name.parse::<Field>(), weight.parse::<f64>()) {
+                    query.boosts.insert(field, weight);
+                    continue;
+                }
+            }
+
+            // Field restricted word, e.g. `title:rust`, or a symbol definition, `def:parse`.
+            let (field, definition, text) = match word.split_once(':') {
+                Some(("def", text)) => (None, true, text),
+                Some((name, text)) => match name.parse::<Field>() {
+                    Ok(field) => (Some(field), false, text),
+                    Err(_) => (None, false, word.as_str()),
+                },
+                None => (None, false, word.as_str()),
+            };
+
+            // The code tokenizer keeps identifiers whole and adds their sub-words, so the
+            // same query matches both prose and code.
+            // Symbol names are code, they keep their stop words.
+            let terms = analyzer.terms_with(text, Tokenizer::Code, drop_stop_words && !definition);
+            // CJK words are split into bigrams, which only make sense together.
+            let is_phrase = text.starts_with('"') || text.chars().any(lexer::is_cjk);
+            if is_phrase && terms.len() > 1 {
+                // A phrase, positions are made relative to its first term.
+                let first = terms[0].position;
+                let terms = terms
+                    .into_iter()
+                    .map(|term| Term {
+                        position: term.position - first,
+                        ..term
+                    })
+                    .collect();
+                query.clauses.push(Clause {
+                    field,
+                    definition,
+                    terms,
+                });
+            } else {
+                for term in terms {
+                    query.clauses.push(Clause {
+                        field,
+                        definition,
+                        terms: vec![Term { position: 0, ..term }],
+                    });
+                }
+            }
+        }
+
+        query
+    }
+
+    /// Looks for definitions of every clause, like writing `def:` before each word.
+    pub fn find_definitions(&mut self) {
+        for clause in &mut self.clauses {
+            clause.definition = true;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// Resolves the boost of a field: the query's own boost first, then the collection default.
+    pub fn boost(&self, field: Field, defaults: &HashMap<Field, f64>) -> f64 {
+        self.boosts
+            .get(&field)
+            .or_else(|| defaults.get(&field))
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::AnalysisSettings;
+
+    // The field, `def:` flag and terms with their positions of every clause.
+    type Parsed<'a> = (Option<Field>, bool, Vec<(&'a str, u32)>);
+
+    fn clauses(query: &Query) -> Vec<Parsed<'_>> {
+        query
+            .clauses
+            .iter()
+            .map(|clause| {
+                let terms = clause.terms.iter().map(|term| (term.text.as_str(), term.position)).collect();
+                (clause.field, clause.definition, terms)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn phrases_keep_the_gaps_of_their_stop_words() {
+        let analyzer = Analyzer::new(&AnalysisSettings::default());
+        let query = Query::parse("title:\"the war of the worlds\" wells", &analyzer);
+        assert_eq!(
+            clauses(&query),
+            vec![
+                (Some(Field::Title), false, vec![("war", 0), ("worlds", 3)]),
+                (None, false, vec![("wells", 0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_query_of_stop_words_only_keeps_them() {
+        let analyzer = Analyzer::new(&AnalysisSettings::default());
+        let query = Query::parse("for the", &analyzer);
+        assert_eq!(clauses(&query), vec![(None, false, vec![("for", 0)]), (None, false, vec![("the", 0)])]);
+        assert!(Query::parse("!!! ...", &analyzer).is_empty());
+    }
+
+    #[test]
+    fn parses_boosts_and_definitions() {
+        let analyzer = Analyzer::new(&AnalysisSettings::default());
+        let query = Query::parse("def:all comment:todo title^3", &analyzer);
+        assert_eq!(
+            clauses(&query),
+            vec![
+                (None, true, vec![("all", 0)]),
+                (Some(Field::Comment), false, vec![("todo", 0)]),
+            ]
+        );
+        assert_eq!(query.boosts.get(&Field::Title), Some(&3.0));
+    }
+}
@@ -0,0 +1,202 @@
+use crate::parsers::Field;
+use std::ops::Range;
+
+/// The kind of a region of a code file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Code,
+    Comment,
+    String,
+}
+
+impl Region {
+    pub const ALL: [Region; 3] = [Region::Code, Region::Comment, Region::String];
+
+    /// The field the text of this kind of region is indexed in.
+    pub fn field(&self) -> Field {
+        match self {
+            Region::Code => Field::Code,
+            Region::Comment => Field::Comment,
+            Region::String => Field::String,
+        }
+    }
+}
+
+/// How comments and string literals are written in a language, see `languages`.
+#[derive(Debug)]
+pub struct Syntax {
+    pub line_comments: &'static [&'static str],
+    pub block_comments: &'static [(&'static str, &'static str)],
+    /// String delimiters, longest first (`"""` before `"`). Backslash escapes apply inside.
+    pub strings: &'static [&'static str],
+}
+
+/// Splits a code file into code, comment and string regions, in order and covering the whole text.
+///
+/// Comment delimiters are part of the comment and quotes are part of the string.
+pub fn classify(code: &str, syntax: &Syntax) -> Vec<(Region, Range<usize>)> {
+    let mut regions: Vec<(Region, Range<usize>)> = Vec::new();
+    let mut push = |region: Region, range: Range<usize>| match regions.last_mut() {
+        Some((last, last_range)) if *last == region && last_range.end == range.start => {
+            last_range.end = range.end
+        }
+        _ => regions.push((region, range)),
+    };
+
+    let mut i = 0;
+    while i < code.len() {
+        let rest = &code[i..];
+        // Block comments first, Lua's `--[[` also starts with the line comment marker.
+        if let Some((open, close)) = syntax
+            .block_comments
+            .iter()
+            .find(|(open, _)| rest.starts_with(*open))
+        {
+            let end = rest[open.len()..]
+                .find(close)
+                .map_or(code.len(), |n| i + open.len() + n + close.len());
+            push(Region::Comment, i..end);
+            i = end;
+        } else if let Some(marker) = syntax.line_comments.iter().find(|marker| rest.starts_with(**marker)) {
+            let end = rest[marker.len()..]
+                .find('\n')
+                .map_or(code.len(), |n| i + marker.len() + n);
+            push(Region::Comment, i..end);
+            i = end;
+        } else if let Some(quote) = syntax.strings.iter().find(|quote| rest.starts_with(**quote)) {
+            // Find the closing quote, skipping escaped characters.
+            let mut end = code.len();
+            let mut chars = rest.char_indices().skip(quote.chars().count());
+            while let Some((n, c)) = chars.next() {
+                if c == '\\' {
+                    chars.next();
+                } else if rest[n..].starts_with(*quote) {
+                    end = i + n + quote.len();
+                    break;
+                } else if c == '\n' && quote.len() == 1 && *quote != "`" {
+                    // An unterminated literal ends with its line.
+                    end = i + n;
+                    break;
+                }
+            }
+            push(Region::String, i..end);
+            i = end;
+        } else {
+            let c = rest.chars().next().unwrap_or_default();
+            push(Region::Code, i..i + c.len_utf8());
+            i += c.len_utf8();
+        }
+    }
+    regions
+}
+
+/// The text of one kind of region, everything else replaced by whitespace of the same
+/// byte length so offsets and line numbers still point into the original text.
+///
+/// A four byte character becomes two whitespace characters, count columns in the
+/// original text with `column`.
+pub fn mask(code: &str, regions: &[(Region, Range<usize>)], kind: Region) -> String {
+    let mut masked = String::with_capacity(code.len());
+    for (region, range) in regions {
+        let text = &code[range.clone()];
+        if *region == kind {
+            masked.push_str(text);
+            continue;
+        }
+        for c in text.chars() {
+            match c.len_utf8() {
+                _ if c == '\n' => masked.push('\n'),
+                1 => masked.push(' '),
+                // No-break space and en quad are whitespace of two and three bytes.
+                2 => masked.push('\u{a0}'),
+                3 => masked.push('\u{2000}'),
+                _ => masked.push_str("\u{2000} "),
+            }
+        }
+    }
+    masked
+}
+
+/// The 1-based column, in characters, of a byte offset of `text`.
+pub fn column(text: &str, offset: usize) -> u32 {
+    let before = text.get(..offset).unwrap_or(text);
+    let line_start = before.rfind('\n').map_or(0, |n| n + 1);
+    before[line_start..].chars().count() as u32 + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYNTAX: Syntax = Syntax {
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        strings: &["\"", "'"],
+    };
+
+    fn texts<'a>(code: &'a str, regions: &[(Region, Range<usize>)]) -> Vec<(Region, &'a str)> {
+        regions.iter().map(|(region, range)| (*region, &code[range.clone()])).collect()
+    }
+
+    #[test]
+    fn classifies_comments_and_strings() {
+        let code = "let s = \"a \\\" // b\"; // done\n/* x */ f('c')";
+        assert_eq!(
+            texts(code, &classify(code, &SYNTAX)),
+            vec![
+                (Region::Code, "let s = "),
+                (Region::String, "\"a \\\" // b\""),
+                (Region::Code, "; "),
+                (Region::Comment, "// done"),
+                (Region::Code, "\n"),
+                (Region::Comment, "/* x */"),
+                (Region::Code, " f("),
+                (Region::String, "'c'"),
+                (Region::Code, ")"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_literals_end_with_their_line() {
+        let code = "x = \"open\ny /* never closed";
+        assert_eq!(
+            texts(code, &classify(code, &SYNTAX)),
+            vec![
+                (Region::Code, "x = "),
+                (Region::String, "\"open"),
+                (Region::Code, "\ny "),
+                (Region::Comment, "/* never closed"),
+            ]
+        );
+    }
+
+    #[test]
+    fn masks_other_regions_keeping_byte_offsets_and_lines() {
+        let code = "é = \"ü😀\"; // ñ\nfoo";
+        let regions = classify(code, &SYNTAX);
+        let masked = mask(code, &regions, Region::Code);
+        assert_eq!(masked.len(), code.len());
+        assert_eq!(masked.lines().count(), code.lines().count());
+        assert_eq!(masked.trim_end_matches("foo").split_whitespace().collect::<Vec<_>>(), ["é", "=", ";"]);
+        assert_eq!(masked.find("foo"), code.find("foo"));
+
+        let comment = mask(code, &regions, Region::Comment);
+        assert_eq!(comment.find("// ñ"), code.find("// ñ"));
+        assert!(comment.chars().filter(|c| !c.is_whitespace()).eq("//ñ".chars()));
+    }
+
+    #[test]
+    fn columns_are_counted_in_characters_of_the_line() {
+        let code = "\"😀😀\"; x\n  ñy";
+        assert_eq!(column(code, code.find('x').unwrap()), 7);
+        assert_eq!(column(code, code.find('y').unwrap()), 4);
+        assert_eq!(column(code, 0), 1);
+
+        // The masked text has more characters, its columns would be off.
+        let masked = mask(code, &classify(code, &SYNTAX), Region::Code);
+        let x = masked.find('x').unwrap();
+        assert_eq!(x, code.find('x').unwrap());
+        assert_ne!(column(&masked, x), column(code, x));
+    }
+}
@@ -0,0 +1,417 @@
+use crate::analysis::Tokenizer;
+use crate::config::{CollectionConfig, UnknownFiles};
+use crate::error::Error;
+use crate::languages::{self, Language};
+use crate::parsers::{self, ParsedDocument};
+use crate::sniff::{self, Sniffed};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Broad category of a format, code files are tokenized as code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    Document,
+    Code,
+}
+
+impl FormatKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FormatKind::Document => "document",
+            FormatKind::Code => "code",
+        }
+    }
+}
+
+/// Turns a file into a `ParsedDocument`.
+pub trait DocumentParser: Send + Sync {
+    /// Human readable name of the format, used in messages and by `seroost formats`.
+    fn name(&self) -> &str;
+
+    fn kind(&self) -> FormatKind {
+        FormatKind::Document
+    }
+
+    /// The tokenizer for the documents of this parser, code gets the identifier aware one.
+    fn tokenizer(&self) -> Tokenizer {
+        match self.kind() {
+            FormatKind::Document => Tokenizer::Prose,
+            FormatKind::Code => Tokenizer::Code,
+        }
+    }
+
+    /// Whether the body is the text of the file line for line, so the lines of matches are
+    /// lines of the file. Extracted text, like that of a PDF or an HTML page, is not.
+    fn keeps_lines(&self) -> bool {
+        self.kind() == FormatKind::Code
+    }
+
+    fn parse(&self, path: &Path) -> Result<ParsedDocument, Error>;
+}
+
+pub struct PdfParser;
+pub struct TextParser;
+pub struct XmlParser;
+pub struct HtmlParser;
+
+/// Parses the languages of `languages::LANGUAGES`, and extra extensions mapped to one of them.
+#[derive(Default)]
+pub struct CodeParser {
+    extra_extensions: HashMap<String, &'static Language>,
+}
+
+impl CodeParser {
+    /// A parser that also handles the given extensions, mapped to language names.
+    /// An unknown language name is an `Error::Config`.
+    pub fn with_extensions(extensions: &HashMap<String, String>) -> Result<Self, Error> {
+        let mut extra_extensions = HashMap::new();
+        for (ext, name) in extensions {
+            let language = Language::by_name(name)
+                .ok_or_else(|| Error::Config(format!("unknown language `{name}` for extension `{ext}`")))?;
+            extra_extensions.insert(ext.trim_start_matches('.').to_lowercase(), language);
+        }
+        Ok(Self { extra_extensions })
+    }
+
+    /// Every extension the parser handles.
+    pub fn extensions(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        let mut extra = self.extra_extensions.keys().map(String::as_str).collect::<Vec<_>>();
+        extra.sort_unstable();
+        languages::LANGUAGES
+            .iter()
+            .flat_map(|language| language.extensions.iter().copied())
+            .chain(extra)
+            .filter(|ext| seen.insert(*ext))
+            .collect()
+    }
+
+    /// The language of a file by its extension, or else by the type its name or
+    /// shebang line give it, like `Makefile` or `#!/bin/sh`.
+    fn language(&self, path: &Path) -> Option<&'static Language> {
+        let by_extension = path.extension().and_then(|ext| {
+            let ext = ext.to_string_lossy().to_lowercase();
+            self.extra_extensions
+                .get(&ext)
+                .copied()
+                .or_else(|| Language::for_extension(&ext))
+        });
+        by_extension.or_else(|| match sniff::sniff(path) {
+            Ok(Sniffed::MimeType(mime_type)) => Language::for_mime_type(mime_type),
+            _ => None,
+        })
+    }
+}
+
+impl DocumentParser for PdfParser {
+    fn name(&self) -> &str {
+        "PDF"
+    }
+
+    fn parse(&self, path: &Path) -> Result<ParsedDocument, Error> {
+        parsers::read_entire_pdf_file(path)
+    }
+}
+
+impl DocumentParser for TextParser {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn keeps_lines(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, path: &Path) -> Result<ParsedDocument, Error> {
+        parsers::read_entire_txt_file(path)
+    }
+}
+
+impl DocumentParser for XmlParser {
+    fn name(&self) -> &str {
+        "XML"
+    }
+
+    fn parse(&self, path: &Path) -> Result<ParsedDocument, Error> {
+        parsers::read_entire_xml_file(path)
+    }
+}
+
+impl DocumentParser for HtmlParser {
+    fn name(&self) -> &str {
+        "HTML"
+    }
+
+    fn parse(&self, path: &Path) -> Result<ParsedDocument, Error> {
+        Ok(parsers::read_entire_html_file(path)?)
+    }
+}
+
+impl DocumentParser for CodeParser {
+    fn name(&self) -> &str {
+        "code"
+    }
+
+    fn kind(&self) -> FormatKind {
+        FormatKind::Code
+    }
+
+    fn parse(&self, path: &Path) -> Result<ParsedDocument, Error> {
+        Ok(parsers::read_code_file(path, self.language(path))?)
+    }
+}
+
+/// Runs a user configured command and indexes its output.
+pub struct ExternalParser {
+    name: String,
+    command: String,
+    timeout: Duration,
+}
+
+impl ExternalParser {
+    pub fn new(command: &str, timeout: Duration) -> Self {
+        let program = command.split_whitespace().next().unwrap_or_default();
+        Self {
+            name: format!("external ({program})"),
+            command: command.to_string(),
+            timeout,
+        }
+    }
+}
+
+impl DocumentParser for ExternalParser {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn parse(&self, path: &Path) -> Result<ParsedDocument, Error> {
+        parsers::read_with_external_command(path, &self.command, self.timeout)
+    }
+}
+
+/// A parser together with the extensions and MIME types it was registered for.
+pub struct Format {
+    pub parser: Arc<dyn DocumentParser>,
+    pub extensions: Vec<String>,
+    pub mime_types: Vec<String>,
+}
+
+/// The outcome of looking up the parser of a file.
+pub enum Detection<'a> {
+    Parser(&'a dyn DocumentParser),
+    /// The file is binary and cannot be indexed.
+    Binary,
+    /// No parser handles the file.
+    Unknown,
+}
+
+/// Maps extensions and MIME types to the parser that handles them.
+pub struct ParserRegistry {
+    formats: Vec<Format>,
+    by_extension: HashMap<String, usize>,
+    by_mime_type: HashMap<String, usize>,
+    unknown_files: UnknownFiles,
+}
+
+impl Default for ParserRegistry {
+    /// A registry with every built-in format.
+    fn default() -> Self {
+        Self::builtin(CodeParser::default())
+    }
+}
+
+impl ParserRegistry {
+    /// An empty registry.
+    pub fn empty() -> Self {
+        Self {
+            formats: Vec::new(),
+            by_extension: HashMap::new(),
+            by_mime_type: HashMap::new(),
+            unknown_files: UnknownFiles::default(),
+        }
+    }
+
+    // The built-in formats, with the given code parser for the languages.
+    fn builtin(code_parser: CodeParser) -> Self {
+        let mut registry = Self::empty();
+        registry.register(PdfParser, &["pdf"], &["application/pdf"]);
+        registry.register(TextParser, &["txt"], &["text/plain"]);
+        registry.register(
+            XmlParser,
+            &["xml", "xhtml"],
+            &["application/xml", "text/xml", "application/xhtml+xml"],
+        );
+        registry.register(HtmlParser, &["html", "htm"], &["text/html"]);
+        let extensions = code_parser
+            .extensions()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let extensions = extensions.iter().map(String::as_str).collect::<Vec<_>>();
+        let mime_types = languages::LANGUAGES
+            .iter()
+            .flat_map(|language| language.mime_types.iter().copied())
+            .collect::<Vec<_>>();
+        registry.register(code_parser, &extensions, &mime_types);
+        registry
+    }
+
+    /// The built-in formats with the collection's settings, extra code extensions
+    /// and external extractors applied. Fails on a language name it does not know.
+    pub fn from_config(collection: &CollectionConfig) -> Result<Self, Error> {
+        let mut registry = Self {
+            unknown_files: collection.unknown_files,
+            ..Self::builtin(CodeParser::with_extensions(&collection.languages)?)
+        };
+        for (ext, command) in &collection.extractors {
+            let parser = ExternalParser::new(command, collection.extractor_timeout());
+            registry.register(parser, &[ext.trim_start_matches('.')], &[]);
+        }
+        Ok(registry)
+    }
+
+    /// Registers a parser, later registrations win for extensions and MIME types registered twice.
+    pub fn register<P>(&mut self, parser: P, extensions: &[&str], mime_types: &[&str])
+    where
+        P: DocumentParser + 'static,
+    {
+        let position = self.formats.len();
+        for ext in extensions {
+            self.by_extension.insert(ext.to_lowercase(), position);
+        }
+        for mime_type in mime_types {
+            self.by_mime_type.insert(mime_type.to_lowercase(), position);
+        }
+        self.formats.push(Format {
+            parser: Arc::new(parser),
+            extensions: extensions.iter().map(|ext| ext.to_lowercase()).collect(),
+            mime_types: mime_types.iter().map(|mime| mime.to_lowercase()).collect(),
+        });
+    }
+
+    pub fn for_extension(&self, ext: &str) -> Option<&dyn DocumentParser> {
+        self.by_extension
+            .get(&ext.to_lowercase())
+            .map(|&position| self.formats[position].parser.as_ref())
+    }
+
+    pub fn for_mime_type(&self, mime_type: &str) -> Option<&dyn DocumentParser> {
+        self.by_mime_type
+            .get(&mime_type.to_lowercase())
+            .map(|&position| self.formats[position].parser.as_ref())
+    }
+
+    /// Looks the parser up by the extension of a path.
+    pub fn for_path<P: AsRef<Path>>(&self, path: P) -> Option<&dyn DocumentParser> {
+        let ext = path.as_ref().extension()?;
+        self.for_extension(&ext.to_string_lossy())
+    }
+
+    /// Looks the parser up by extension first, then by the file's name and content.
+    ///
+    /// Textual files of unknown type are handed to the plain text parser unless
+    /// the collection is configured to skip them.
+    pub fn detect<P: AsRef<Path>>(&self, path: P) -> io::Result<Detection<'_>> {
+        if let Some(parser) = self.for_path(&path) {
+            return Ok(Detection::Parser(parser));
+        }
+
+        let parser = match sniff::sniff(&path)? {
+            Sniffed::Binary => return Ok(Detection::Binary),
+            Sniffed::MimeType(mime_type) => self.for_mime_type(mime_type),
+            Sniffed::Text => None,
+        };
+        let parser = parser.or_else(|| match self.unknown_files {
+            UnknownFiles::Text => self.for_mime_type("text/plain"),
+            UnknownFiles::Skip => None,
+        });
+
+        Ok(parser.map_or(Detection::Unknown, Detection::Parser))
+    }
+
+    pub fn formats(&self) -> &[Format] {
+        &self.formats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn detected(registry: &ParserRegistry, path: &Path) -> String {
+        match registry.detect(path).unwrap() {
+            Detection::Parser(parser) => parser.name().to_string(),
+            Detection::Binary => "binary".to_string(),
+            Detection::Unknown => "unknown".to_string(),
+        }
+    }
+
+    #[test]
+    fn looks_up_built_in_formats_ignoring_case() {
+        let registry = ParserRegistry::default();
+        assert_eq!(registry.for_extension("PDF").map(|parser| parser.name()), Some("PDF"));
+        assert_eq!(registry.for_path("src/main.RS").map(|parser| parser.name()), Some("code"));
+        assert_eq!(registry.for_mime_type("Text/HTML").map(|parser| parser.name()), Some("HTML"));
+        assert!(registry.for_extension("docx").is_none());
+        assert!(registry.for_path("Makefile").is_none());
+    }
+
+    #[test]
+    fn later_registrations_win() {
+        let mut registry = ParserRegistry::empty();
+        registry.register(TextParser, &["md", "txt"], &["text/markdown"]);
+        registry.register(HtmlParser, &["MD"], &[]);
+        assert_eq!(registry.for_extension("md").map(|parser| parser.name()), Some("HTML"));
+        assert_eq!(registry.for_extension("txt").map(|parser| parser.name()), Some("text"));
+        assert_eq!(registry.for_mime_type("text/markdown").map(|parser| parser.name()), Some("text"));
+        assert_eq!(registry.formats().len(), 2);
+    }
+
+    #[test]
+    fn detects_by_extension_before_content() {
+        let dir = std::env::temp_dir().join(format!("seroost-registry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, content: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let mut registry = ParserRegistry::default();
+
+        // The extension decides, whatever the content looks like.
+        assert_eq!(detected(&registry, &file("page.txt", b"<html><p>hi</p></html>")), "text");
+        assert_eq!(detected(&registry, &file("page", b"<html><p>hi</p></html>")), "HTML");
+        assert_eq!(detected(&registry, &file("blob", b"\x00\x01\x02\x03")), "binary");
+        assert_eq!(detected(&registry, &file("notes", b"just words")), "text");
+
+        registry.unknown_files = UnknownFiles::Skip;
+        assert_eq!(detected(&registry, &dir.join("notes")), "unknown");
+        assert_eq!(detected(&registry, &dir.join("page")), "HTML");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn external_parsers_read_the_output_of_their_command() {
+        let dir = std::env::temp_dir().join(format!("seroost-external-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("report.docx");
+        fs::write(&path, "Quarterly report\nsales went up").unwrap();
+        let timeout = Duration::from_secs(5);
+
+        let parsed = ExternalParser::new("cat {path}", timeout).parse(&path).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("Quarterly report"));
+        assert_eq!(parsed.body, "Quarterly report\nsales went up");
+
+        let parser = ExternalParser::new("false {path}", timeout);
+        assert_eq!(parser.name(), "external (false)");
+        assert!(matches!(parser.parse(&path), Err(Error::Parse(message)) if message.contains("exited with")));
+
+        let parser = ExternalParser::new("sleep 10", Duration::from_millis(100));
+        assert!(matches!(parser.parse(&path), Err(Error::Parse(message)) if message.contains("timed out")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
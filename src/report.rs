@@ -0,0 +1,192 @@
+use crate::error::Error;
+use crate::index;
+use crate::indexer::{Progress, SkipReason};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A file left out of the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    /// Lowercased extension, empty when the file has none.
+    pub extension: String,
+    /// The kind of `SkipReason`, like `parse_error`.
+    pub reason: String,
+    pub message: String,
+}
+
+/// A file indexed despite a problem.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileWarning {
+    pub path: String,
+    pub message: String,
+}
+
+/// What happened to the files of the last indexing run, saved next to the index.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexReport {
+    /// The indexed directory.
+    pub root: String,
+    /// Number of documents in the index.
+    pub indexed: usize,
+    pub skipped: Vec<SkippedFile>,
+    #[serde(default)]
+    pub warnings: Vec<FileWarning>,
+}
+
+impl IndexReport {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_string_lossy().into_owned(),
+            ..Self::default()
+        }
+    }
+
+    /// Counts an indexed file, or records a skipped one or a warning.
+    pub fn record(&mut self, progress: &Progress) {
+        match progress {
+            Progress::Indexed { indexed, .. } => self.indexed = *indexed,
+            Progress::Skipped { path, reason } => self.skip(path, reason),
+            Progress::Warning { path, message } => self.warnings.push(FileWarning {
+                path: path.clone(),
+                message: message.clone(),
+            }),
+        }
+    }
+
+    fn skip(&mut self, path: &str, reason: &SkipReason) {
+        let extension = Path::new(path).extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        self.skipped.push(SkippedFile {
+            path: path.to_string(),
+            extension: extension.unwrap_or_default(),
+            reason: reason.kind().to_string(),
+            message: reason.to_string(),
+        });
+    }
+
+    /// Skipped files per reason.
+    pub fn by_reason(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for file in &self.skipped {
+            *counts.entry(file.reason.as_str()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Skipped files per extension, and per reason within each extension.
+    pub fn by_extension(&self) -> BTreeMap<&str, BTreeMap<&str, usize>> {
+        let mut counts: BTreeMap<&str, BTreeMap<&str, usize>> = BTreeMap::new();
+        for file in &self.skipped {
+            *counts
+                .entry(file.extension.as_str())
+                .or_default()
+                .entry(file.reason.as_str())
+                .or_default() += 1;
+        }
+        counts
+    }
+
+    /// The report with its counts, for tools reading `seroost report --json`.
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "root": self.root,
+            "indexed": self.indexed,
+            "skipped": self.skipped.len(),
+            "by_reason": self.by_reason(),
+            "by_extension": self.by_extension(),
+            "files": self.skipped,
+            "warnings": self.warnings,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(report_path: P) -> Result<Self, Error> {
+        let file = fs::File::open(&report_path)?;
+        serde_json::from_reader(io::BufReader::new(file)).map_err(|e| {
+            Error::CorruptIndex(format!(
+                "could not read report {}: {e}. Please run index again.",
+                report_path.as_ref().display()
+            ))
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, report_path: P) -> Result<(), Error> {
+        if let Some(parent) = report_path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::File::create(report_path)?;
+        serde_json::to_writer_pretty(io::BufWriter::new(file), self).map_err(io::Error::from)?;
+        Ok(())
+    }
+}
+
+/// The report of the last indexing run, next to the index file.
+pub fn get_report_path() -> PathBuf {
+    report_path(&index::get_indeces_path())
+}
+
+/// The report saved next to an index file.
+pub fn report_path(index_path: &Path) -> PathBuf {
+    index_path.with_file_name("report.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skipped(path: &str, reason: SkipReason) -> Progress {
+        Progress::Skipped { path: path.to_string(), reason }
+    }
+
+    fn parse_error(message: &str) -> SkipReason {
+        SkipReason::ParseError { format: "PDF".to_string(), message: message.to_string() }
+    }
+
+    fn report() -> IndexReport {
+        let mut report = IndexReport::new(Path::new("docs"));
+        for progress in [
+            Progress::Indexed { path: "docs/a.txt".to_string(), indexed: 1 },
+            skipped("docs/b.PDF", parse_error("bad xref")),
+            skipped("docs/c.pdf", SkipReason::TooLarge { bytes: 1 << 30 }),
+            skipped("docs/d.pdf", parse_error("encrypted")),
+            skipped("docs/e.bin", SkipReason::Binary),
+            skipped("docs/Makefile", SkipReason::UnknownType),
+            Progress::Indexed { path: "docs/f.txt".to_string(), indexed: 2 },
+            Progress::Warning { path: "docs/f.txt".to_string(), message: "not UTF-8".to_string() },
+        ] {
+            report.record(&progress);
+        }
+        report
+    }
+
+    #[test]
+    fn counts_skipped_files_by_reason_and_extension() {
+        let report = report();
+        assert_eq!(report.indexed, 2);
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(
+            report.by_reason(),
+            BTreeMap::from([("binary", 1), ("parse_error", 2), ("too_large", 1), ("unknown_type", 1)])
+        );
+        assert_eq!(
+            report.by_extension(),
+            BTreeMap::from([
+                ("", BTreeMap::from([("unknown_type", 1)])),
+                ("bin", BTreeMap::from([("binary", 1)])),
+                ("pdf", BTreeMap::from([("parse_error", 2), ("too_large", 1)])),
+            ])
+        );
+    }
+
+    #[test]
+    fn the_summary_has_the_counts() {
+        let summary = report().summary();
+        assert_eq!(summary["indexed"], 2);
+        assert_eq!(summary["skipped"], 5);
+        assert_eq!(summary["by_reason"]["parse_error"], 2);
+        assert_eq!(summary["by_extension"]["pdf"]["too_large"], 1);
+        assert_eq!(summary["files"][0]["extension"], "pdf");
+    }
+}
@@ -0,0 +1,409 @@
+use crate::daemon::SearchRequest;
+use seroost::analysis::Analyzer;
+use seroost::config::CollectionConfig;
+use seroost::index::{self, Index};
+use seroost::indexer::{Indexer, Progress, Reporter};
+use seroost::Error;
+use seroost::registry::ParserRegistry;
+use seroost::report::{self, IndexReport};
+use seroost::searcher::Searcher;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+// Error codes of the JSON-RPC 2.0 specification.
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+// Seroost errors, in the range the specification leaves to servers.
+pub const INDEX_NOT_FOUND: i64 = -32001;
+pub const INVALID_QUERY: i64 = -32002;
+
+/// An error answered to a request.
+#[derive(Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn internal(error: impl std::fmt::Display) -> Self {
+        Self::new(INTERNAL_ERROR, error.to_string())
+    }
+}
+
+/// Parameters of a request, `null` when there are none.
+pub fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+/// Writes messages to stdout, shared with the indexing threads sending notifications.
+/// Messages go out like the last one came in: one per line, or after a `Content-Length`
+/// header as LSP clients expect.
+#[derive(Clone, Default)]
+pub struct Output {
+    framed: Arc<AtomicBool>,
+}
+
+impl Output {
+    fn send(&self, message: &Value) {
+        let body = message.to_string();
+        let mut stdout = io::stdout().lock();
+        let written = if self.framed.load(Ordering::Relaxed) {
+            write!(stdout, "Content-Length: {}\r\n\r\n{body}", body.len())
+        } else {
+            writeln!(stdout, "{body}")
+        };
+        // When the client went away the next read ends the session.
+        let _ = written.and_then(|_| stdout.flush());
+    }
+
+    pub fn notify(&self, method: &str, params: Value) {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    pub fn respond(&self, id: Value, result: Result<Value, RpcError>) {
+        let message = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": error.code, "message": error.message },
+            }),
+        };
+        self.send(&message);
+    }
+
+    /// Reads the next message from stdin, `None` at the end of the input.
+    pub fn receive(&self, input: &mut impl BufRead) -> Option<io::Result<String>> {
+        loop {
+            let mut line = String::new();
+            match input.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some(length) = header_length(line) else {
+                self.framed.store(false, Ordering::Relaxed);
+                return Some(Ok(line.to_string()));
+            };
+
+            // Skip the other headers up to the blank line, then read the body.
+            self.framed.store(true, Ordering::Relaxed);
+            loop {
+                let mut header = String::new();
+                match input.read_line(&mut header) {
+                    Ok(0) => return None,
+                    Ok(_) if header.trim().is_empty() => break,
+                    Ok(_) => {}
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            let mut body = vec![0; length];
+            return Some(
+                input
+                    .read_exact(&mut body)
+                    .map(|_| String::from_utf8_lossy(&body).into_owned()),
+            );
+        }
+    }
+}
+
+fn header_length(line: &str) -> Option<usize> {
+    let (name, value) = line.split_once(':')?;
+    name.eq_ignore_ascii_case("content-length")
+        .then(|| value.trim().parse().ok())
+        .flatten()
+}
+
+/// A request or notification, checked against JSON-RPC 2.0.
+pub struct Message {
+    /// `None` for notifications, which get no answer.
+    pub id: Option<Value>,
+    pub method: String,
+    pub params: Value,
+}
+
+impl Message {
+    pub fn parse(text: &str) -> Result<Self, RpcError> {
+        let value: Value = serde_json::from_str(text).map_err(|e| RpcError::new(PARSE_ERROR, e.to_string()))?;
+        let invalid = || RpcError::new(INVALID_REQUEST, "expected a JSON-RPC 2.0 request object");
+        if value.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Err(invalid());
+        }
+        let method = value.get("method").and_then(Value::as_str).ok_or_else(invalid)?;
+        Ok(Self {
+            id: value.get("id").cloned(),
+            method: method.to_string(),
+            params: value.get("params").cloned().unwrap_or(Value::Null),
+        })
+    }
+}
+
+/// The index of the collection, loaded on first use and reloaded when the file changes.
+pub struct Session {
+    pub root: String,
+    pub max_file_size: u64,
+    pub collection: CollectionConfig,
+    pub registry: Arc<ParserRegistry>,
+    pub output: Output,
+    /// Where the index is saved, the report goes next to it.
+    pub index_path: PathBuf,
+    loaded: Option<(Searcher, Option<SystemTime>)>,
+}
+
+fn modified_time(index_path: &Path) -> Option<SystemTime> {
+    fs::metadata(index_path).and_then(|m| m.modified()).ok()
+}
+
+impl Session {
+    pub fn new(root: String, max_file_size: u64, collection: CollectionConfig, registry: Arc<ParserRegistry>) -> Self {
+        Self {
+            root,
+            index_path: index::get_indeces_path(),
+            max_file_size,
+            collection,
+            registry,
+            output: Output::default(),
+            loaded: None,
+        }
+    }
+
+    /// Loads the index unless the loaded one is still the one on disk.
+    fn load(&mut self) -> Result<(), RpcError> {
+        let modified = modified_time(&self.index_path);
+        if self.loaded.as_ref().is_some_and(|(_, loaded)| *loaded == modified) {
+            return Ok(());
+        }
+        let index_path = &self.index_path;
+        if !index_path.exists() {
+            return Err(RpcError::new(
+                INDEX_NOT_FOUND,
+                "index file not found. Please run index first.",
+            ));
+        }
+        let searcher = Searcher::load(index_path).map_err(RpcError::internal)?;
+        let searcher = searcher
+            .with_boosts(self.collection.boosts.clone())
+            .with_analysis(self.collection.analysis.clone());
+        self.loaded = Some((searcher, modified));
+        Ok(())
+    }
+
+    /// The searcher of the loaded index.
+    pub fn searcher(&mut self) -> Result<&Searcher, RpcError> {
+        self.load()?;
+        match &self.loaded {
+            Some((searcher, _)) => Ok(searcher),
+            None => Err(RpcError::new(INDEX_NOT_FOUND, "the index is not loaded")),
+        }
+    }
+
+    /// Saves the index and keeps it as the loaded one.
+    fn store(&mut self, index: Index) -> Result<(), RpcError> {
+        index.save(&self.index_path).map_err(RpcError::internal)?;
+        let searcher = Searcher::new(index)
+            .with_boosts(self.collection.boosts.clone())
+            .with_analysis(self.collection.analysis.clone());
+        self.loaded = Some((searcher, modified_time(&self.index_path)));
+        Ok(())
+    }
+
+    /// `search`: the parameters of a daemon request, answered with the same results
+    /// plus the text of the matching lines of code files.
+    pub fn search(&mut self, params: Value) -> Result<Value, RpcError> {
+        let request: SearchRequest = self::params(params)?;
+        let registry = Arc::clone(&self.registry);
+        let searcher = self.searcher()?;
+        let mut response = request
+            .run(searcher)
+            .map_err(|err| RpcError::new(INVALID_QUERY, err.to_string()))?;
+        for hit in &mut response.results {
+            hit.read_lines(&registry);
+        }
+        serde_json::to_value(response).map_err(RpcError::internal)
+    }
+
+    /// `index`: indexes the collection again, sending a `progress` notification for every file.
+    pub fn reindex(&mut self) -> Result<Value, RpcError> {
+        let output = self.output.clone();
+        let report = Arc::new(move |progress: Progress| match progress {
+            Progress::Indexed { path, indexed } => output.notify(
+                "progress",
+                json!({ "kind": "indexed", "path": path, "indexed": indexed }),
+            ),
+            Progress::Skipped { path, reason } => output.notify(
+                "progress",
+                json!({ "kind": "skipped", "path": path, "reason": reason.to_string() }),
+            ),
+            Progress::Warning { path, message } => output.notify(
+                "progress",
+                json!({ "kind": "warning", "path": path, "message": message }),
+            ),
+        });
+
+        let report = self.build(report)?;
+        Ok(json!({ "documents": report.indexed, "skipped": report.skipped.len(), "by_reason": report.by_reason() }))
+    }
+
+    fn indexer(&self) -> Indexer {
+        Indexer::new(Arc::clone(&self.registry), self.collection.analysis.clone()).max_file_size(self.max_file_size)
+    }
+
+    /// Indexes the collection and saves it with its report, which is returned.
+    pub fn build(&mut self, report: Reporter) -> Result<IndexReport, RpcError> {
+        let (index, report) = self
+            .indexer()
+            .on_progress(report)
+            .index_dir(&self.root)
+            .map_err(RpcError::internal)?;
+        self.store(index)?;
+        report.save(report::report_path(&self.index_path)).map_err(RpcError::internal)?;
+        Ok(report)
+    }
+
+    /// `reindexFile`: parses one file again, or drops it from the index when it is gone
+    /// or can no longer be indexed.
+    pub fn reindex_file(&mut self, path: &Path) -> Result<Value, RpcError> {
+        // Start a new index when there is none yet.
+        let mut index = match self.load() {
+            Ok(()) => self.loaded.take().map(|(searcher, _)| searcher.into_index()),
+            Err(e) if e.code == INDEX_NOT_FOUND => None,
+            Err(e) => return Err(e),
+        }
+        .unwrap_or_else(|| Index::new(self.collection.analysis.clone()));
+
+        let result = if !path.is_file() {
+            json!({ "path": path, "indexed": false, "removed": index.documents.remove(path).is_some() })
+        } else {
+            match self.indexer().parse_file(path) {
+                Ok(mut document) => {
+                    document.strip_root(&self.root);
+                    let analyzer = Analyzer::new(&index.analysis);
+                    index.add_document(path.to_path_buf(), &document, &analyzer);
+                    json!({ "path": path, "indexed": true })
+                }
+                Err(reason) => json!({
+                    "path": path,
+                    "indexed": false,
+                    "removed": index.documents.remove(path).is_some(),
+                    "reason": reason.to_string(),
+                }),
+            }
+        };
+        self.store(index)?;
+        Ok(result)
+    }
+
+    fn dispatch(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "search" => self.search(params),
+            "index" => self.reindex(),
+            "reindexFile" => {
+                #[derive(serde::Deserialize)]
+                struct File {
+                    path: PathBuf,
+                }
+                let file: File = self::params(params)?;
+                self.reindex_file(&file.path)
+            }
+            "stats" => Ok(self.searcher()?.index().stats()),
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method `{method}`"))),
+        }
+    }
+}
+
+/// Answers JSON-RPC 2.0 requests on stdin and stdout until stdin closes.
+pub fn run(
+    root: String,
+    max_file_size: u64,
+    collection: CollectionConfig,
+    registry: Arc<ParserRegistry>,
+) -> Result<(), Error> {
+    let mut session = Session::new(root, max_file_size, collection, registry);
+    let mut input = io::stdin().lock();
+    while let Some(text) = session.output.receive(&mut input) {
+        let message = match Message::parse(&text?) {
+            Ok(message) => message,
+            Err(error) => {
+                session.output.respond(Value::Null, Err(error));
+                continue;
+            }
+        };
+        let result = session.dispatch(&message.method, message.params);
+        if let Some(id) = message.id {
+            session.output.respond(id, result);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_line_delimited_messages() {
+        let output = Output::default();
+        let mut input = io::Cursor::new("{\"a\": 1}\n\n  {\"b\": 2}  \n");
+        assert_eq!(output.receive(&mut input).unwrap().unwrap(), "{\"a\": 1}");
+        assert_eq!(output.receive(&mut input).unwrap().unwrap(), "{\"b\": 2}");
+        assert!(output.receive(&mut input).is_none());
+        assert!(!output.framed.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn reads_content_length_framed_messages() {
+        let output = Output::default();
+        let first = "{\"id\":1,\"q\":\"ü\"}";
+        let second = "{\"id\":2}";
+        let text = format!(
+            "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc\r\n\r\n{first}content-length:{}\r\n\r\n{second}",
+            first.len(),
+            second.len()
+        );
+        let mut input = io::Cursor::new(text);
+        assert_eq!(output.receive(&mut input).unwrap().unwrap(), first);
+        assert!(output.framed.load(Ordering::Relaxed));
+        assert_eq!(output.receive(&mut input).unwrap().unwrap(), second);
+        assert!(output.receive(&mut input).is_none());
+    }
+
+    #[test]
+    fn a_truncated_body_is_an_error() {
+        let output = Output::default();
+        let mut input = io::Cursor::new("Content-Length: 20\r\n\r\n{\"id\":1}");
+        assert!(output.receive(&mut input).unwrap().is_err());
+    }
+
+    #[test]
+    fn checks_requests_against_json_rpc() {
+        let message = Message::parse(r#"{"jsonrpc": "2.0", "id": 7, "method": "search", "params": {"query": "x"}}"#).unwrap();
+        assert_eq!((message.id, message.method.as_str()), (Some(json!(7)), "search"));
+        assert_eq!(message.params, json!({ "query": "x" }));
+
+        let notification = Message::parse(r#"{"jsonrpc": "2.0", "method": "exit"}"#).unwrap();
+        assert_eq!((notification.id, notification.params), (None, Value::Null));
+
+        assert_eq!(Message::parse("{").err().map(|e| e.code), Some(PARSE_ERROR));
+        assert_eq!(Message::parse(r#"{"id": 1, "method": "x"}"#).err().map(|e| e.code), Some(INVALID_REQUEST));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::analysis::{AnalysisSettings, Analyzer};
+use crate::error::Error;
+use crate::index::{Index, SearchHit};
+use crate::parsers::Field;
+use crate::query::Query;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A page of ranked results.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchResults {
+    /// Number of matching documents, the hits are a page of them.
+    pub total: usize,
+    pub hits: Vec<SearchHit>,
+    /// Set when the index was built with other analysis settings than the collection has now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Keeps the results of some files only, the default keeps every file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    /// Extensions to keep, lowercased and without the dot; empty keeps every extension.
+    pub extensions: Vec<String>,
+    /// Text the path has to contain.
+    pub path: String,
+}
+
+impl Filter {
+    pub fn keeps(&self, path: &Path) -> bool {
+        let ext = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+        (self.extensions.is_empty() || self.extensions.contains(&ext)) && path.to_string_lossy().contains(&self.path)
+    }
+}
+
+/// Answers queries against a loaded index, analyzing them like its documents were.
+pub struct Searcher {
+    index: Index,
+    analyzer: Analyzer,
+    boosts: HashMap<Field, f64>,
+    analysis: Option<AnalysisSettings>,
+}
+
+impl Searcher {
+    pub fn new(index: Index) -> Self {
+        Self {
+            analyzer: Analyzer::new(&index.analysis),
+            index,
+            boosts: HashMap::new(),
+            analysis: None,
+        }
+    }
+
+    /// Loads a saved index, see `Index::save`.
+    pub fn load<P: AsRef<Path>>(index_path: P) -> Result<Self, Error> {
+        Ok(Self::new(Index::load(index_path)?))
+    }
+
+    /// Default field boosts, the query's own `field^weight` boosts override them.
+    pub fn with_boosts(mut self, boosts: HashMap<Field, f64>) -> Self {
+        self.boosts = boosts;
+        self
+    }
+
+    /// The analysis the collection is configured with, results warn when the index was built with another.
+    pub fn with_analysis(mut self, analysis: AnalysisSettings) -> Self {
+        self.analysis = Some(analysis);
+        self
+    }
+
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    pub fn analyzer(&self) -> &Analyzer {
+        &self.analyzer
+    }
+
+    pub fn into_index(self) -> Index {
+        self.index
+    }
+
+    /// Parses a query with the analysis of the index.
+    pub fn parse(&self, text: &str) -> Query {
+        Query::parse(text, &self.analyzer)
+    }
+
+    /// Set when the index was built with other analysis settings than the collection has now,
+    /// queries are analyzed like the index was built, not like the current settings say.
+    pub fn warning(&self) -> Option<String> {
+        self.analysis
+            .as_ref()
+            .is_some_and(|analysis| *analysis != self.index.analysis)
+            .then(|| "the analysis settings changed since the index was built, run index again to apply them.".to_string())
+    }
+
+    /// Ranks the matching documents and returns `limit` of them from `offset`,
+    /// with their matching lines filled in.
+    pub fn search(&self, query: &Query, limit: usize, offset: usize) -> SearchResults {
+        self.search_filtered(query, &Filter::default(), limit, offset)
+    }
+
+    /// Like `search`, for the documents the filter keeps.
+    pub fn search_filtered(&self, query: &Query, filter: &Filter, limit: usize, offset: usize) -> SearchResults {
+        let mut hits = self.index.search(query, &self.boosts);
+        hits.retain(|hit| filter.keeps(&hit.path));
+        let total = hits.len();
+        let mut hits = hits.into_iter().skip(offset).take(limit).collect::<Vec<_>>();
+        for hit in &mut hits {
+            if let Some(doc) = self.index.documents.get(&hit.path) {
+                hit.line_matches = doc.line_matches(query);
+            }
+        }
+        SearchResults {
+            total,
+            hits,
+            warning: self.warning(),
+        }
+    }
+
+    /// Parses and ranks a query, with `definitions` looking for definitions of every word
+    /// like `--symbol`. A query without search terms is an `Error::Query`.
+    pub fn search_text(
+        &self,
+        text: &str,
+        definitions: bool,
+        filter: &Filter,
+        limit: usize,
+        offset: usize,
+    ) -> Result<SearchResults, Error> {
+        let mut query = self.parse(text);
+        if definitions {
+            query.find_definitions();
+        }
+        if query.is_empty() {
+            return Err(Error::Query("No valid search terms found.".to_string()));
+        }
+        Ok(self.search_filtered(&query, filter, limit, offset))
+    }
+}
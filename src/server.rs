@@ -0,0 +1,409 @@
+use crate::percent;
+use seroost::config::CollectionConfig;
+use seroost::index;
+use seroost::Error;
+use seroost::registry::{Detection, ParserRegistry};
+use seroost::searcher::{Filter, Searcher};
+use colored::Colorize;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+const DEFAULT_LIMIT: usize = 10;
+
+// Bytes of extracted text kept in memory, the least recently shown documents go first.
+const TEXT_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+// Bytes of context shown before and after the first match of a snippet.
+const SNIPPET_BEFORE: usize = 80;
+const SNIPPET_AFTER: usize = 160;
+
+// The single page UI, compiled into the binary.
+const ASSETS: &[(&str, &str, &str)] = &[
+    ("/", "text/html; charset=utf-8", include_str!("ui/index.html")),
+    ("/app.js", "text/javascript; charset=utf-8", include_str!("ui/app.js")),
+    ("/style.css", "text/css; charset=utf-8", include_str!("ui/style.css")),
+];
+
+/// Decodes a `application/x-www-form-urlencoded` component: `+` is a space and `%XX` a byte.
+fn decode_component(component: &str) -> String {
+    percent::decode(component, true)
+}
+
+/// Splits a request URL into its path and decoded query parameters.
+fn parse_url(url: &str) -> (&str, HashMap<String, String>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_component(key), decode_component(value))
+        })
+        .collect();
+    (path, params)
+}
+
+fn response(status: u16, content_type: &str, body: String) -> Response<Cursor<Vec<u8>>> {
+    let mut response = Response::from_string(body).with_status_code(StatusCode(status));
+    if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
+        response.add_header(header);
+    }
+    response
+}
+
+fn json_response(status: u16, body: &Value) -> Response<Cursor<Vec<u8>>> {
+    response(status, "application/json; charset=utf-8", body.to_string())
+}
+
+fn floor_boundary(text: &str, mut i: usize) -> usize {
+    i = i.min(text.len());
+    while !text.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn ceil_boundary(text: &str, mut i: usize) -> usize {
+    i = i.min(text.len());
+    while !text.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+/// Cuts `start..end` of a text into `{"text", "match"}` segments, the matches being the
+/// given byte ranges. Ranges from an index older than the file are clamped to the text.
+fn segments(text: &str, matches: &[(u32, u32)], start: usize, end: usize) -> Vec<Value> {
+    let mut segments = Vec::new();
+    let mut at = start;
+    for &(match_start, match_end) in matches {
+        let match_start = floor_boundary(text, (match_start as usize).max(at));
+        let match_end = ceil_boundary(text, (match_end as usize).min(end));
+        if match_start >= match_end {
+            continue;
+        }
+        if at < match_start {
+            segments.push(json!({ "text": &text[at..match_start], "match": false }));
+        }
+        segments.push(json!({ "text": &text[match_start..match_end], "match": true }));
+        at = match_end;
+    }
+    if at < end {
+        segments.push(json!({ "text": &text[at..end], "match": false }));
+    }
+    segments
+}
+
+/// A few lines of context around the first match.
+fn snippet(text: &str, matches: &[(u32, u32)]) -> Vec<Value> {
+    let Some(&(first, _)) = matches.first() else {
+        return Vec::new();
+    };
+    let start = floor_boundary(text, (first as usize).saturating_sub(SNIPPET_BEFORE));
+    // A match at the edge of the window is not cut in half.
+    let end = matches
+        .iter()
+        .filter(|(match_start, _)| (*match_start as usize) < first as usize + SNIPPET_AFTER)
+        .map(|(_, match_end)| *match_end as usize)
+        .fold(first as usize + SNIPPET_AFTER, usize::max);
+    segments(text, matches, start, ceil_boundary(text, end))
+}
+
+fn error_response(status: u16, message: &str) -> Response<Cursor<Vec<u8>>> {
+    json_response(status, &json!({ "error": message }))
+}
+
+/// Extracted texts of the documents shown so far, up to `TEXT_CACHE_BYTES`.
+#[derive(Default)]
+struct TextCache {
+    texts: HashMap<PathBuf, Result<Rc<str>, String>>,
+    /// Cached paths, least recently used first.
+    order: VecDeque<PathBuf>,
+    bytes: usize,
+}
+
+impl TextCache {
+    fn get(&mut self, path: &Path) -> Option<Result<Rc<str>, String>> {
+        let text = self.texts.get(path)?.clone();
+        if let Some(position) = self.order.iter().position(|cached| cached == path) {
+            if let Some(path) = self.order.remove(position) {
+                self.order.push_back(path);
+            }
+        }
+        Some(text)
+    }
+
+    fn insert(&mut self, path: PathBuf, text: Result<Rc<str>, String>) {
+        self.bytes += cached_len(&text);
+        if let Some(replaced) = self.texts.insert(path.clone(), text) {
+            self.bytes -= cached_len(&replaced);
+            self.order.retain(|cached| *cached != path);
+        }
+        self.order.push_back(path);
+        // The newest text stays even when it is larger than the whole cache.
+        while self.bytes > TEXT_CACHE_BYTES && self.order.len() > 1 {
+            let Some(oldest) = self.order.pop_front() else { break };
+            if let Some(evicted) = self.texts.remove(&oldest) {
+                self.bytes -= cached_len(&evicted);
+            }
+        }
+    }
+}
+
+fn cached_len(text: &Result<Rc<str>, String>) -> usize {
+    match text {
+        Ok(text) => text.len(),
+        Err(message) => message.len(),
+    }
+}
+
+/// A loaded index answering the API, shared by every request.
+struct State<'a> {
+    searcher: Searcher,
+    registry: &'a ParserRegistry,
+    stats: Value,
+    /// Extracted texts of the documents shown lately, the index only keeps their terms.
+    texts: RefCell<TextCache>,
+}
+
+impl State<'_> {
+    /// The text of a document as its parser extracts it, cached after the first call.
+    fn text(&self, path: &Path) -> Result<Rc<str>, String> {
+        if let Some(text) = self.texts.borrow_mut().get(path) {
+            return text;
+        }
+        let text = match self.registry.detect(path) {
+            Ok(Detection::Parser(parser)) => parser
+                .parse(path)
+                .map(|document| Rc::from(document.body))
+                .map_err(|e| e.to_string()),
+            Ok(_) => Err("the file can no longer be parsed".to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        self.texts.borrow_mut().insert(path.to_path_buf(), text.clone());
+        text
+    }
+
+    fn search(&self, params: &HashMap<String, String>) -> Response<Cursor<Vec<u8>>> {
+        let Some(text) = params.get("q").filter(|q| !q.trim().is_empty()) else {
+            return error_response(400, "missing query parameter `q`");
+        };
+        let number = |name: &str, default: usize| match params.get(name) {
+            Some(value) => value.parse::<usize>().map_err(|_| format!("`{name}` must be a number")),
+            None => Ok(default),
+        };
+        let (limit, offset) = match (number("limit", DEFAULT_LIMIT), number("offset", 0)) {
+            (Ok(limit), Ok(offset)) => (limit, offset),
+            (Err(message), _) | (_, Err(message)) => return error_response(400, &message),
+        };
+
+        // Filters: `ext=rs,py` keeps those extensions, `path=src/` paths containing the text.
+        let filter = Filter {
+            extensions: params
+                .get("ext")
+                .map(|ext| {
+                    ext.split(',')
+                        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                        .filter(|ext| !ext.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            path: params.get("path").map(|path| path.trim().to_string()).unwrap_or_default(),
+        };
+
+        let found = match self.searcher.search_text(text, false, &filter, limit, offset) {
+            Ok(found) => found,
+            Err(Error::Query(message)) => return error_response(400, &message),
+            Err(e) => return error_response(500, &e.to_string()),
+        };
+        // The highlighted snippets need the matches of the query in the text.
+        let query = self.searcher.parse(text);
+        let results = found
+            .hits
+            .iter()
+            .map(|hit| {
+                let doc = self.searcher.index().documents.get(&hit.path);
+                let matches = doc.map(|doc| doc.text_matches(&query)).unwrap_or_default();
+                let snippet = match matches.is_empty() {
+                    true => Vec::new(),
+                    false => self
+                        .text(&hit.path)
+                        .map(|text| snippet(&text, &matches))
+                        .unwrap_or_default(),
+                };
+                let line_matches = hit
+                    .line_matches
+                    .iter()
+                    .map(|line_match| {
+                        json!({
+                            "line": line_match.line,
+                            "terms": line_match.terms,
+                            "regions": line_match.fields,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                json!({
+                    "id": hit.path.to_string_lossy(),
+                    "path": hit.path.to_string_lossy(),
+                    "title": hit.title,
+                    "score": hit.score,
+                    "definitions": hit.definitions,
+                    "snippet": snippet,
+                    "line_matches": line_matches,
+                })
+            })
+            .collect::<Vec<_>>();
+        json_response(
+            200,
+            &json!({
+                "query": text,
+                "total": found.total,
+                "offset": offset,
+                "limit": limit,
+                "results": results,
+                "warning": found.warning,
+            }),
+        )
+    }
+
+    fn document(&self, params: &HashMap<String, String>) -> Response<Cursor<Vec<u8>>> {
+        let Some(id) = params.get("id") else {
+            return error_response(400, "missing query parameter `id`");
+        };
+        let Some((path, doc)) = self.searcher.index().documents.get_key_value(Path::new(id)) else {
+            return error_response(404, "no such document in the index");
+        };
+        let fields = doc
+            .fields
+            .iter()
+            .map(|(field, field_index)| (field.name(), field_index.len))
+            .collect::<HashMap<_, _>>();
+        // With a query the text comes as segments with the matches highlighted.
+        let (text, segments, error) = match self.text(path) {
+            Ok(text) => {
+                let segments = params.get("q").map(|q| {
+                    let query = self.searcher.parse(q);
+                    segments(&text, &doc.text_matches(&query), 0, text.len())
+                });
+                (Some(text.to_string()), segments, None)
+            }
+            Err(error) => (None, None, Some(error)),
+        };
+        json_response(
+            200,
+            &json!({
+                "id": path.to_string_lossy(),
+                "path": path.to_string_lossy(),
+                "title": doc.title,
+                "fields": fields,
+                "symbols": doc.symbols,
+                "text": text,
+                "segments": segments,
+                "error": error,
+            }),
+        )
+    }
+
+    fn handle(&self, request: &Request) -> Response<Cursor<Vec<u8>>> {
+        if *request.method() != Method::Get {
+            return error_response(405, "only GET is supported");
+        }
+        let (path, params) = parse_url(request.url());
+        if let Some((_, content_type, body)) = ASSETS.iter().find(|(asset, _, _)| *asset == path) {
+            return response(200, content_type, body.to_string());
+        }
+        match path {
+            "/api/search" => self.search(&params),
+            "/api/doc" => self.document(&params),
+            "/api/stats" => json_response(200, &self.stats),
+            _ => error_response(404, "not found"),
+        }
+    }
+}
+
+/// Loads the index once and answers `/api/search`, `/api/doc` and `/api/stats` with JSON,
+/// next to the search page at `/`.
+pub fn serve(host: &str, port: u16, collection: &CollectionConfig, registry: &ParserRegistry) -> Result<(), Error> {
+    let index_path = index::get_indeces_path();
+    println!("{} {}", "Loading search index:".blue(), index_path.to_string_lossy());
+    let searcher = Searcher::load(&index_path)?
+        .with_boosts(collection.boosts.clone())
+        .with_analysis(collection.analysis.clone());
+    if let Some(warning) = searcher.warning() {
+        eprintln!("{}", format!("Warning: {warning}").yellow());
+    }
+
+    let state = State {
+        stats: searcher.index().stats(),
+        searcher,
+        registry,
+        texts: RefCell::new(TextCache::default()),
+    };
+
+    // IPv6 addresses are written in brackets next to a port.
+    let address = if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
+    let server = Server::http(&address).map_err(|e| io::Error::other(format!("could not listen on {address}: {e}")))?;
+    println!(
+        "{} {} {}",
+        "Serving".green().bold(),
+        state.searcher.index().len().to_string().yellow().bold(),
+        format!("documents on http://{address}/").green().bold()
+    );
+
+    for request in server.incoming_requests() {
+        let response = state.handle(&request);
+        println!("{} {} {}", request.method(), request.url(), response.status_code().0);
+        if let Err(e) = request.respond(response) {
+            eprintln!("{} {e}", "Error answering request:".red());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_form_components() {
+        assert_eq!(decode_component("def%3Aparse+query"), "def:parse query");
+        assert_eq!(decode_component("caf%C3%A9%20%E4%B8%AD"), "café 中");
+        // Malformed escapes are kept as they are.
+        assert_eq!(decode_component("100%+sure%zz%4"), "100% sure%zz%4");
+        assert_eq!(decode_component("%41"), "A");
+        assert_eq!(decode_component("%+1"), "% 1");
+    }
+
+    #[test]
+    fn splits_urls_into_path_and_parameters() {
+        let (path, params) = parse_url("/api/search?q=hello+world&ext=rs%2Cpy&limit=5");
+        assert_eq!(path, "/api/search");
+        assert_eq!(params["q"], "hello world");
+        assert_eq!(params["ext"], "rs,py");
+        assert_eq!(params["limit"], "5");
+        assert!(parse_url("/").1.is_empty());
+    }
+
+    #[test]
+    fn text_cache_drops_the_least_recently_shown_texts() {
+        let mut cache = TextCache::default();
+        let large = |c: char| Ok(Rc::from(c.to_string().repeat(TEXT_CACHE_BYTES / 3).as_str()));
+        cache.insert(PathBuf::from("a"), large('a'));
+        cache.insert(PathBuf::from("b"), large('b'));
+        cache.insert(PathBuf::from("c"), large('c'));
+        assert!(cache.get(Path::new("a")).is_some());
+        cache.insert(PathBuf::from("d"), large('d'));
+        assert!(cache.get(Path::new("b")).is_none());
+        assert!(cache.get(Path::new("a")).is_some());
+        assert!(cache.bytes <= TEXT_CACHE_BYTES);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::encoding;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+// Only the beginning of a file is looked at.
+const SNIFF_LEN: u64 = 8192;
+
+/// What the name and the first bytes of a file say about its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sniffed {
+    /// A recognised type.
+    MimeType(&'static str),
+    /// Text of no particular type.
+    Text,
+    /// Not text, should not be indexed.
+    Binary,
+}
+
+// Well known extensionless file names, compared in lowercase.
+const FILE_NAMES: &[(&str, &str)] = &[
+    ("makefile", "text/x-makefile"),
+    ("gnumakefile", "text/x-makefile"),
+    ("dockerfile", "text/x-dockerfile"),
+    ("containerfile", "text/x-dockerfile"),
+    ("rakefile", "text/x-ruby"),
+    ("gemfile", "text/x-ruby"),
+    ("readme", "text/plain"),
+    ("license", "text/plain"),
+    ("copying", "text/plain"),
+    ("authors", "text/plain"),
+    ("changelog", "text/plain"),
+    ("todo", "text/plain"),
+];
+
+// Interpreters named by a shebang line.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "text/x-python"),
+    ("sh", "text/x-shellscript"),
+    ("bash", "text/x-shellscript"),
+    ("zsh", "text/x-shellscript"),
+    ("dash", "text/x-shellscript"),
+    ("ksh", "text/x-shellscript"),
+    ("node", "text/javascript"),
+    ("deno", "text/javascript"),
+    ("ruby", "text/x-ruby"),
+    ("php", "application/x-httpd-php"),
+];
+
+/// Detects the type of a file from its shebang line and magic bytes, then from its name.
+///
+/// The content always goes first: a name only gives plain text a type, so
+/// `README.gz` or `license.png` are still skipped as binaries.
+pub fn sniff<P: AsRef<Path>>(path: P) -> io::Result<Sniffed> {
+    let path = path.as_ref();
+
+    let mut head = Vec::new();
+    fs::File::open(path)?.take(SNIFF_LEN).read_to_end(&mut head)?;
+    let sniffed = sniff_bytes(&head);
+    if sniffed != Sniffed::Text {
+        return Ok(sniffed);
+    }
+
+    // Makefile, Dockerfile, README... by their full name only.
+    if let Some(name) = path.file_name() {
+        let name = name.to_string_lossy().to_lowercase();
+        if let Some((_, mime_type)) = FILE_NAMES.iter().find(|(known, _)| name == *known) {
+            return Ok(Sniffed::MimeType(mime_type));
+        }
+    }
+    Ok(sniffed)
+}
+
+/// Detects the type of a file from its first bytes.
+pub fn sniff_bytes(head: &[u8]) -> Sniffed {
+    if head.starts_with(b"%PDF-") {
+        return Sniffed::MimeType("application/pdf");
+    }
+    if let Some(mime_type) = shebang_mime_type(head) {
+        return Sniffed::MimeType(mime_type);
+    }
+    if is_binary(head) {
+        return Sniffed::Binary;
+    }
+
+    // Markup, possibly after a byte order mark and leading whitespace.
+    let text = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let start = text
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(text.len());
+    let prefix = text[start..]
+        .iter()
+        .take(512)
+        .map(|byte| byte.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    if prefix.starts_with(b"<!doctype html") || prefix.starts_with(b"<html") {
+        return Sniffed::MimeType("text/html");
+    }
+    if prefix.starts_with(b"<?xml") {
+        if prefix.windows(5).any(|window| window == b"<html") {
+            return Sniffed::MimeType("application/xhtml+xml");
+        }
+        return Sniffed::MimeType("application/xml");
+    }
+
+    Sniffed::Text
+}
+
+// Maps `#!/usr/bin/env python3` or `#!/bin/sh -e` to a MIME type.
+fn shebang_mime_type(head: &[u8]) -> Option<&'static str> {
+    let line = head.strip_prefix(b"#!")?;
+    let line = &line[..line.iter().position(|&b| b == b'\n').unwrap_or(line.len())];
+    let line = String::from_utf8_lossy(line);
+    let mut words = line.split_whitespace();
+
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    // python3.11 -> python
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    INTERPRETERS
+        .iter()
+        .find(|(name, _)| *name == program)
+        .map(|(_, mime_type)| *mime_type)
+}
+
+// Binary files contain NUL bytes or many control characters, except UTF-16 text.
+fn is_binary(head: &[u8]) -> bool {
+    if head.starts_with(b"\xFF\xFE") || head.starts_with(b"\xFE\xFF") {
+        return false;
+    }
+    if encoding::detect_utf16(head).is_some() {
+        return false;
+    }
+    if head.contains(&0) {
+        return true;
+    }
+    let control = head
+        .iter()
+        .filter(|&&byte| byte < 0x20 && !matches!(byte, b'\n' | b'\r' | b'\t' | 0x0C))
+        .count();
+    control * 10 > head.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_magic_bytes_and_markup() {
+        assert_eq!(sniff_bytes(b"%PDF-1.7\n%\xE2\xE3"), Sniffed::MimeType("application/pdf"));
+        assert_eq!(sniff_bytes(b"\xEF\xBB\xBF\n  <!DOCTYPE html><p>"), Sniffed::MimeType("text/html"));
+        assert_eq!(sniff_bytes(b"<?xml version=\"1.0\"?><note/>"), Sniffed::MimeType("application/xml"));
+        assert_eq!(
+            sniff_bytes(b"<?xml version=\"1.0\"?>\n<html xmlns=\"http://www.w3.org/1999/xhtml\">"),
+            Sniffed::MimeType("application/xhtml+xml")
+        );
+        assert_eq!(sniff_bytes("plain words, ünïcode".as_bytes()), Sniffed::Text);
+    }
+
+    #[test]
+    fn reads_the_interpreter_of_shebang_lines() {
+        assert_eq!(sniff_bytes(b"#!/bin/sh -e\necho"), Sniffed::MimeType("text/x-shellscript"));
+        assert_eq!(sniff_bytes(b"#!/usr/bin/env -S python3.11 -u\n"), Sniffed::MimeType("text/x-python"));
+        assert_eq!(sniff_bytes(b"#!/usr/bin/env node"), Sniffed::MimeType("text/javascript"));
+        assert_eq!(sniff_bytes(b"#!/usr/bin/awk -f\n"), Sniffed::Text);
+    }
+
+    #[test]
+    fn tells_binaries_from_utf16_text() {
+        assert_eq!(sniff_bytes(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Sniffed::Binary);
+        assert_eq!(sniff_bytes(b"\x1f\x8b\x08\x00\x01\x02\x03\x04\x05\x06"), Sniffed::Binary);
+        let utf16 = "utf-16 text".encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<_>>();
+        assert_eq!(sniff_bytes(&utf16), Sniffed::Text);
+    }
+
+    #[test]
+    fn names_only_apply_to_text_content() {
+        let dir = std::env::temp_dir().join(format!("seroost-sniff-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let sniff_file = |name: &str, content: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            sniff(&path).unwrap()
+        };
+        assert_eq!(sniff_file("Makefile", b"all:\n\tcc x.c\n"), Sniffed::MimeType("text/x-makefile"));
+        assert_eq!(sniff_file("README", b"Read me"), Sniffed::MimeType("text/plain"));
+        assert_eq!(sniff_file("README.gz", b"\x1f\x8b\x08\x00\0\0\0\0"), Sniffed::Binary);
+        assert_eq!(sniff_file("todo.sqlite", b"SQLite format 3\0\0\x01"), Sniffed::Binary);
+        // Only the full name counts, not the name without its extension.
+        assert_eq!(sniff_file("license.txt2", b"MIT"), Sniffed::Text);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+use crate::analysis::Language;
+
+const ENGLISH: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "am", "an", "and", "any", "are",
+    "as", "at", "be", "because", "been", "before", "being", "below", "between", "both", "but",
+    "by", "can", "could", "did", "do", "does", "doing", "down", "during", "each", "few", "for",
+    "from", "further", "had", "has", "have", "having", "he", "her", "here", "hers", "herself",
+    "him", "himself", "his", "how", "i", "if", "in", "into", "is", "it", "its", "itself", "just",
+    "me", "more", "most", "my", "myself", "no", "nor", "not", "now", "of", "off", "on", "once",
+    "only", "or", "other", "our", "ours", "ourselves", "out", "over", "own", "same", "she",
+    "should", "so", "some", "such", "than", "that", "the", "their", "theirs", "them",
+    "themselves", "then", "there", "these", "they", "this", "those", "through", "to", "too",
+    "under", "until", "up", "very", "was", "we", "were", "what", "when", "where", "which",
+    "while", "who", "whom", "why", "will", "with", "would", "you", "your", "yours", "yourself",
+    "yourselves",
+];
+
+const FRENCH: &[&str] = &[
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "et", "eux", "il",
+    "ils", "je", "la", "le", "les", "leur", "lui", "ma", "mais", "me", "même", "mes", "moi",
+    "mon", "ne", "nos", "notre", "nous", "on", "ou", "par", "pas", "pour", "qu", "que", "qui",
+    "sa", "se", "ses", "son", "sur", "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vos",
+    "votre", "vous", "c", "d", "j", "l", "m", "n", "s", "t", "y", "est", "sont", "été", "être",
+];
+
+const GERMAN: &[&str] = &[
+    "aber", "alle", "als", "also", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "da",
+    "damit", "dann", "das", "dass", "dem", "den", "der", "des", "die", "dies", "diese", "doch",
+    "du", "durch", "ein", "eine", "einem", "einen", "einer", "eines", "er", "es", "für", "hat",
+    "hatte", "ich", "ihr", "im", "in", "ist", "ja", "kann", "mit", "nach", "nicht", "noch", "nur",
+    "ob", "oder", "sein", "sich", "sie", "sind", "so", "um", "und", "uns", "von", "vor", "war",
+    "was", "wenn", "wie", "wir", "wird", "zu", "zum", "zur",
+];
+
+const SPANISH: &[&str] = &[
+    "a", "al", "algo", "como", "con", "de", "del", "el", "ella", "ellos", "en", "entre", "era",
+    "es", "esa", "ese", "eso", "esta", "este", "esto", "fue", "ha", "hay", "la", "las", "le",
+    "les", "lo", "los", "me", "mi", "muy", "más", "ni", "no", "nos", "o", "para", "pero", "por",
+    "que", "qué", "se", "sin", "sobre", "su", "sus", "también", "te", "tu", "un", "una", "uno",
+    "y", "ya", "yo",
+];
+
+const ITALIAN: &[&str] = &[
+    "a", "ad", "al", "alla", "anche", "che", "chi", "con", "cui", "da", "dal", "dalla", "de",
+    "degli", "dei", "del", "della", "di", "e", "ed", "gli", "ha", "i", "il", "in", "io", "la",
+    "le", "lei", "lo", "lui", "ma", "mi", "ne", "nel", "nella", "noi", "non", "o", "per", "più",
+    "quella", "quello", "questa", "questo", "se", "si", "sono", "su", "sua", "suo", "tra", "tu",
+    "un", "una", "uno", "è",
+];
+
+const PORTUGUESE: &[&str] = &[
+    "a", "ao", "aos", "as", "com", "como", "da", "das", "de", "do", "dos", "e", "ela", "ele",
+    "em", "entre", "era", "essa", "esse", "esta", "este", "eu", "foi", "isso", "já", "lhe", "mais",
+    "mas", "me", "na", "nas", "no", "nos", "não", "o", "os", "ou", "para", "pela", "pelo", "por",
+    "que", "se", "sem", "seu", "sua", "são", "também", "um", "uma", "é",
+];
+
+const DUTCH: &[&str] = &[
+    "aan", "al", "als", "bij", "dat", "de", "der", "die", "dit", "een", "en", "er", "had", "heb",
+    "hem", "het", "hij", "hoe", "hun", "ik", "in", "is", "je", "kan", "maar", "me", "men", "met",
+    "mij", "na", "naar", "niet", "nog", "nu", "of", "om", "omdat", "ons", "ook", "op", "over",
+    "te", "tot", "uit", "van", "voor", "was", "wat", "we", "wel", "werd", "wij", "zal", "ze",
+    "zich", "zij", "zijn", "zo",
+];
+
+/// The built-in stop words of a language, empty for languages without a list.
+pub fn for_language(language: Language) -> &'static [&'static str] {
+    match language {
+        Language::English => ENGLISH,
+        Language::French => FRENCH,
+        Language::German => GERMAN,
+        Language::Spanish => SPANISH,
+        Language::Italian => ITALIAN,
+        Language::Portuguese => PORTUGUESE,
+        Language::Dutch => DUTCH,
+        _ => &[],
+    }
+}
@@ -0,0 +1,423 @@
+use crate::languages::Language;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What a symbol defines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Class,
+    Enum,
+    Interface,
+    Type,
+    Module,
+    Constant,
+}
+
+impl SymbolKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SymbolKind::Function => "function",
+            SymbolKind::Method => "method",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Class => "class",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Interface => "interface",
+            SymbolKind::Type => "type",
+            SymbolKind::Module => "module",
+            SymbolKind::Constant => "constant",
+        }
+    }
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A definition found in a code file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 1-based line of the definition.
+    pub line: u32,
+}
+
+/// Languages that share the way definitions are written, see `languages`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Rust,
+    Python,
+    JavaScript,
+    Go,
+    /// Java and C#: classes, and methods written as C-style signatures.
+    Java,
+    Kotlin,
+    Scala,
+    Swift,
+    /// C and C++.
+    C,
+    Php,
+    Ruby,
+}
+
+impl Syntax {
+    // Keywords followed by the name they define.
+    fn keywords(&self) -> &'static [(&'static str, SymbolKind)] {
+        use SymbolKind::*;
+        match self {
+            Syntax::Rust => &[
+                ("fn", Function), ("struct", Struct), ("enum", Enum), ("trait", Interface),
+                ("type", Type), ("mod", Module), ("const", Constant), ("static", Constant),
+            ],
+            Syntax::Python => &[("def", Function), ("class", Class)],
+            Syntax::JavaScript => &[
+                ("function", Function), ("class", Class), ("interface", Interface),
+                ("type", Type), ("enum", Enum), ("namespace", Module),
+            ],
+            Syntax::Go => &[("func", Function), ("const", Constant)],
+            Syntax::Java => &[
+                ("class", Class), ("interface", Interface), ("enum", Enum), ("struct", Struct),
+                ("record", Class), ("namespace", Module),
+            ],
+            Syntax::Kotlin => &[
+                ("fun", Function), ("class", Class), ("interface", Interface), ("object", Class),
+                ("typealias", Type),
+            ],
+            Syntax::Scala => &[
+                ("def", Function), ("class", Class), ("object", Class), ("trait", Interface),
+                ("type", Type),
+            ],
+            Syntax::Swift => &[
+                ("func", Function), ("class", Class), ("struct", Struct), ("enum", Enum),
+                ("protocol", Interface), ("typealias", Type),
+            ],
+            Syntax::C => &[
+                ("struct", Struct), ("class", Class), ("enum", Enum), ("union", Struct),
+                ("typedef", Type), ("namespace", Module), ("define", Constant),
+            ],
+            Syntax::Php => &[
+                ("function", Function), ("class", Class), ("interface", Interface),
+                ("trait", Interface), ("const", Constant), ("enum", Enum),
+            ],
+            Syntax::Ruby => &[("def", Function), ("class", Class), ("module", Module)],
+        }
+    }
+}
+
+// Words that look like a call or a signature but never start a definition.
+const CONTROL_WORDS: &[&str] = &[
+    "if", "else", "for", "while", "switch", "case", "return", "new", "throw", "delete", "sizeof",
+    "catch", "do", "await", "yield", "typeof", "using",
+];
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+// The identifiers of a line with their byte offsets.
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (is_identifier_char(c), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push((s, &line[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, &line[s..]));
+    }
+    words
+}
+
+// Comment lines are skipped, as are the continuation lines of `/* ... */` blocks.
+fn is_comment(line: &str, language: Option<&Language>) -> bool {
+    let Some(language) = language else {
+        return ["//", "/*", "*", "#"].iter().any(|marker| line.starts_with(marker));
+    };
+    let syntax = &language.syntax;
+    syntax.line_comments.iter().any(|marker| line.starts_with(marker))
+        || syntax.block_comments.iter().any(|(open, _)| line.starts_with(open))
+        || (line.starts_with('*') && syntax.block_comments.iter().any(|(open, _)| *open == "/*"))
+}
+
+fn is_constant_name(name: &str) -> bool {
+    name.chars().any(char::is_alphabetic)
+        && name.chars().all(|c| c.is_uppercase() || c.is_numeric() || c == '_')
+}
+
+// The identifier at the start of `text`, if any.
+fn leading_identifier(text: &str) -> Option<&str> {
+    let end = text.find(|c| !is_identifier_char(c)).unwrap_or(text.len());
+    let name = &text[..end];
+    (!name.is_empty() && !name.starts_with(|c: char| c.is_numeric())).then_some(name)
+}
+
+// The name defined by a C-style signature such as `static int parse(char *s) {` or
+// `void Parser::parse()`; the qualified C++ form is reported as a method.
+fn signature(line: &str) -> Option<(&str, bool)> {
+    if line.ends_with(';') || line.starts_with('#') || line.starts_with("//") || line.starts_with('*') {
+        return None;
+    }
+    let before = line[..line.find('(')?].trim_end();
+    if before.contains(['=', '.', '"', '\'', '!', '|', '&', '+', '-', '{', '}']) {
+        return None;
+    }
+    let start = before
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !is_identifier_char(c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let name = leading_identifier(&before[start..])?;
+    let prefix = before[..start].trim_end();
+    let qualified = prefix.ends_with("::");
+    let first_word = prefix.split_whitespace().next()?;
+    if CONTROL_WORDS.contains(&name) || CONTROL_WORDS.contains(&first_word) {
+        return None;
+    }
+    Some((name, qualified))
+}
+
+/// Finds the definitions of a code file with per-language heuristics.
+///
+/// Functions that are indented (inside a class, impl or object) are reported as methods.
+/// Files of unknown languages fall back to the keywords shared by most languages.
+pub fn extract(code: &str, language: Option<&Language>) -> Vec<Symbol> {
+    let syntax = language.and_then(|language| language.symbols);
+    let keywords = syntax.map_or(
+        &[
+            ("fn", SymbolKind::Function), ("function", SymbolKind::Function),
+            ("def", SymbolKind::Function), ("func", SymbolKind::Function),
+            ("class", SymbolKind::Class), ("struct", SymbolKind::Struct),
+        ][..],
+        |syntax| syntax.keywords(),
+    );
+
+    let mut symbols = Vec::new();
+    for (number, line) in code.lines().enumerate() {
+        let number = number as u32 + 1;
+        let trimmed = line.trim();
+        let indented = line.starts_with(char::is_whitespace);
+        let mut push = |name: &str, kind: SymbolKind| {
+            let kind = match kind {
+                SymbolKind::Function if indented => SymbolKind::Method,
+                kind => kind,
+            };
+            symbols.push(Symbol { name: name.to_string(), kind, line: number });
+        };
+
+        if is_comment(trimmed, language) {
+            continue;
+        }
+        let words = words(trimmed);
+        let mut found = false;
+        for pair in words.windows(2) {
+            let ((offset, keyword), (_, name)) = (pair[0], pair[1]);
+            let Some((_, kind)) = keywords.iter().find(|(k, _)| *k == keyword) else {
+                continue;
+            };
+            // `enum class` and friends, the name comes one word later.
+            if keywords.iter().any(|(k, _)| *k == name) {
+                continue;
+            }
+            // A keyword starts the line or follows a space (`#define` in C), so `'static`
+            // or `obj.type` do not count.
+            let preceding = trimmed[..offset].chars().next_back();
+            if !preceding.is_none_or(|c| c.is_whitespace() || c == '#') {
+                continue;
+            }
+            // Only a keyword followed directly by the name counts, not one in a call.
+            let after = trimmed[offset + keyword.len()..].trim_start();
+            if syntax == Some(Syntax::Go) && keyword == "func" && after.starts_with('(') {
+                // `func (r *Receiver) Name(...)`
+                if let Some(name) = after
+                    .find(')')
+                    .and_then(|i| leading_identifier(after[i + 1..].trim_start()))
+                {
+                    push(name, SymbolKind::Method);
+                    found = true;
+                }
+                break;
+            }
+            if !after.starts_with(name) {
+                continue;
+            }
+            push(name, *kind);
+            found = true;
+            break;
+        }
+        if found {
+            continue;
+        }
+
+        match syntax {
+            // `type Name struct`, `type Name interface`, `type Name int`
+            Some(Syntax::Go) if words.len() >= 3 && words[0].1 == "type" => {
+                let kind = match words[2].1 {
+                    "struct" => SymbolKind::Struct,
+                    "interface" => SymbolKind::Interface,
+                    _ => SymbolKind::Type,
+                };
+                push(words[1].1, kind);
+            }
+            // `const MAX_SIZE = 10`, `const parse = (input) => ...`
+            Some(Syntax::JavaScript) => {
+                let declaration = ["export const ", "const ", "export let ", "let "]
+                    .iter()
+                    .find_map(|prefix| trimmed.strip_prefix(prefix));
+                if let Some(name) = declaration.and_then(leading_identifier) {
+                    if trimmed.contains("=>") || trimmed.contains("function") {
+                        push(name, SymbolKind::Function);
+                    } else if is_constant_name(name) {
+                        push(name, SymbolKind::Constant);
+                    }
+                } else if indented {
+                    // Class methods: `async parse(input) {`
+                    let signature = trimmed
+                        .trim_start_matches("static ")
+                        .trim_start_matches("async ")
+                        .trim_start_matches("get ")
+                        .trim_start_matches("set ");
+                    if let Some(name) = leading_identifier(signature) {
+                        let rest = &signature[name.len()..];
+                        if rest.starts_with('(') && trimmed.ends_with('{') && !CONTROL_WORDS.contains(&name) {
+                            push(name, SymbolKind::Method);
+                        }
+                    }
+                }
+            }
+            // `val MAX = 3` and `const val MAX = 3`
+            Some(Syntax::Kotlin | Syntax::Scala | Syntax::Swift) => {
+                if let Some(name) = ["const val ", "val ", "let ", "static let "]
+                    .iter()
+                    .find_map(|prefix| trimmed.strip_prefix(prefix))
+                    .and_then(leading_identifier)
+                    .filter(|name| is_constant_name(name))
+                {
+                    push(name, SymbolKind::Constant);
+                }
+            }
+            // Top level `MAX_SIZE = 10`
+            Some(Syntax::Python | Syntax::Ruby) if !indented => {
+                if let Some(name) = leading_identifier(trimmed).filter(|name| is_constant_name(name)) {
+                    if trimmed[name.len()..].trim_start().starts_with('=') {
+                        push(name, SymbolKind::Constant);
+                    }
+                }
+            }
+            Some(Syntax::Java | Syntax::C) => {
+                // `static final int MAX_SIZE = 10;`, `const int MAX_SIZE = 10;`
+                if let Some((declaration, _)) = trimmed.split_once('=') {
+                    let is_constant = ["final", "const", "constexpr"]
+                        .iter()
+                        .any(|modifier| declaration.split_whitespace().any(|word| word == *modifier));
+                    if let Some(name) = declaration.split_whitespace().last().filter(|_| is_constant) {
+                        if leading_identifier(name) == Some(name) {
+                            push(name, SymbolKind::Constant);
+                        }
+                    }
+                } else if let Some((name, qualified)) = signature(trimmed) {
+                    push(name, if qualified { SymbolKind::Method } else { SymbolKind::Function });
+                }
+            }
+            _ => {}
+        }
+    }
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(code: &str, ext: &str) -> Vec<(String, SymbolKind, u32)> {
+        extract(code, Language::for_extension(ext))
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.line))
+            .collect()
+    }
+
+    fn symbol(name: &str, kind: SymbolKind, line: u32) -> (String, SymbolKind, u32) {
+        (name.to_string(), kind, line)
+    }
+
+    #[test]
+    fn finds_rust_definitions_and_skips_comments() {
+        let code = "// fn commented() {}\npub struct Index {\n}\nimpl Index {\n    pub fn search(&self) {}\n}\nconst MAX: usize = 3;\nfn main() {}\n";
+        assert_eq!(
+            symbols(code, "rs"),
+            [
+                symbol("Index", SymbolKind::Struct, 2),
+                symbol("search", SymbolKind::Method, 5),
+                symbol("MAX", SymbolKind::Constant, 7),
+                symbol("main", SymbolKind::Function, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_python_and_go_definitions() {
+        let code = "MAX_SIZE = 10\nclass Parser:\n    def parse(self):\n        pass\n";
+        assert_eq!(
+            symbols(code, "py"),
+            [
+                symbol("MAX_SIZE", SymbolKind::Constant, 1),
+                symbol("Parser", SymbolKind::Class, 2),
+                symbol("parse", SymbolKind::Method, 3),
+            ]
+        );
+        let code = "type Index struct {\n}\nfunc (i *Index) Search(q string) {\n}\nfunc main() {\n}\n";
+        assert_eq!(
+            symbols(code, "go"),
+            [
+                symbol("Index", SymbolKind::Struct, 1),
+                symbol("Search", SymbolKind::Method, 3),
+                symbol("main", SymbolKind::Function, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_c_style_signatures() {
+        let code = "static int parse(char *s) {\nvoid Parser::reset() {\n    if (done) {\nint count(void);\nconst int LIMIT = 4;\n";
+        assert_eq!(
+            symbols(code, "cpp"),
+            [
+                symbol("parse", SymbolKind::Function, 1),
+                symbol("reset", SymbolKind::Method, 2),
+                symbol("LIMIT", SymbolKind::Constant, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn signatures_with_multibyte_characters_do_not_panic() {
+        let code = "void “quoted”(int x) {\nint\u{a0}main(void) {\nvoid →parse(int x) {\n";
+        assert_eq!(
+            symbols(code, "c"),
+            [
+                symbol("main", SymbolKind::Function, 2),
+                symbol("parse", SymbolKind::Function, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_languages_use_the_common_keywords() {
+        let code = "def build():\nfunction run() {\nlet x = 1\n";
+        assert_eq!(
+            symbols(code, "unknown"),
+            [symbol("build", SymbolKind::Function, 1), symbol("run", SymbolKind::Function, 2)]
+        );
+    }
+}
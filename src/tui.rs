@@ -0,0 +1,375 @@
+use crate::daemon::SearchRequest;
+use seroost::config::CollectionConfig;
+use seroost::index::{self, SearchHit};
+use seroost::Error;
+use seroost::query::Query;
+use seroost::registry::{Detection, FormatKind, ParserRegistry};
+use seroost::searcher::Searcher;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How many results are ranked for every keystroke.
+const RESULTS: usize = 100;
+
+/// Lines shown above the first match when a preview opens.
+const CONTEXT_LINES: u16 = 3;
+
+/// The text of a document as its parser extracts it, and whether its lines are the lines of the file.
+struct Preview {
+    text: Result<String, String>,
+    code: bool,
+}
+
+struct App<'a> {
+    searcher: Searcher,
+    registry: &'a ParserRegistry,
+    input: String,
+    /// The parsed input, `None` when it has no search terms.
+    query: Option<Query>,
+    hits: Vec<SearchHit>,
+    total: usize,
+    status: String,
+    list: ListState,
+    scroll: u16,
+    /// Height of the preview pane at the last draw, for paging.
+    preview_height: u16,
+    previews: HashMap<PathBuf, Preview>,
+}
+
+impl App<'_> {
+    /// Ranks the documents for the current input, selecting the first hit.
+    fn search(&mut self) {
+        self.hits.clear();
+        self.total = 0;
+        self.query = None;
+        self.status.clear();
+        if !self.input.trim().is_empty() {
+            let request = SearchRequest {
+                query: self.input.clone(),
+                symbol: false,
+                limit: RESULTS,
+                offset: 0,
+            };
+            match request.run(&self.searcher) {
+                Ok(response) => {
+                    self.total = response.total;
+                    self.hits = response.results;
+                    self.status = match self.total {
+                        1 => "1 matching document".to_string(),
+                        total => format!("{total} matching documents"),
+                    };
+                    if let Some(warning) = response.warning {
+                        self.status = format!("{}, warning: {warning}", self.status);
+                    }
+                    self.query = Some(self.searcher.parse(&self.input));
+                }
+                Err(err) => self.status = err.to_string(),
+            }
+        }
+        self.list.select((!self.hits.is_empty()).then_some(0));
+        self.show_selected();
+    }
+
+    fn selected(&self) -> Option<&SearchHit> {
+        self.list.selected().and_then(|i| self.hits.get(i))
+    }
+
+    fn select(&mut self, delta: isize) {
+        if self.hits.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, self.hits.len() as isize - 1);
+        self.list.select(Some(next as usize));
+        self.show_selected();
+    }
+
+    /// Parses the selected document once and scrolls its preview to the first match.
+    fn show_selected(&mut self) {
+        self.scroll = 0;
+        let Some(path) = self.selected().map(|hit| hit.path.clone()) else {
+            return;
+        };
+        if !self.previews.contains_key(&path) {
+            let preview = match self.registry.detect(&path) {
+                Ok(Detection::Parser(parser)) => Preview {
+                    text: parser.parse(&path).map(|document| document.body).map_err(|e| e.to_string()),
+                    code: parser.kind() == FormatKind::Code,
+                },
+                Ok(_) => Preview {
+                    text: Err("the file can no longer be parsed".to_string()),
+                    code: false,
+                },
+                Err(e) => Preview {
+                    text: Err(e.to_string()),
+                    code: false,
+                },
+            };
+            self.previews.insert(path.clone(), preview);
+        }
+        if let Some(line) = self.first_match_line(&path) {
+            self.scroll = (line.saturating_sub(1) as u16).saturating_sub(CONTEXT_LINES);
+        }
+    }
+
+    /// Byte ranges of the query matches in the text of a document.
+    fn matches(&self, path: &Path) -> Vec<(u32, u32)> {
+        match (&self.query, self.searcher.index().documents.get(path)) {
+            (Some(query), Some(doc)) => doc.text_matches(query),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 1-based line of the first match in the preview text.
+    fn first_match_line(&self, path: &Path) -> Option<usize> {
+        let Ok(text) = &self.previews.get(path)?.text else {
+            return None;
+        };
+        let (start, _) = *self.matches(path).first()?;
+        let before = text.get(..start as usize)?;
+        Some(before.matches('\n').count() + 1)
+    }
+
+    /// The line the editor opens a hit at: its first definition, or its first match in a code file.
+    fn editor_line(&self, hit: &SearchHit) -> Option<usize> {
+        if !self.previews.get(&hit.path).is_some_and(|preview| preview.code) {
+            return None;
+        }
+        hit.definitions
+            .first()
+            .map(|symbol| symbol.line as usize)
+            .or_else(|| hit.line_matches.first().map(|line_match| line_match.line as usize))
+            .or_else(|| self.first_match_line(&hit.path))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [input_area, main_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [results_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(main_area);
+
+        let input = Paragraph::new(self.input.as_str()).block(Block::bordered().title(" Search "));
+        frame.render_widget(input, input_area);
+        frame.set_cursor_position((
+            input_area.x + 1 + self.input.chars().count() as u16,
+            input_area.y + 1,
+        ));
+
+        self.draw_results(frame, results_area);
+        self.draw_preview(frame, preview_area);
+
+        let help = " ↑↓ select · PgUp/PgDn scroll · Enter open in $EDITOR · Ctrl-U clear · Esc quit ";
+        let status = Line::from(vec![
+            Span::raw(format!(" {}", self.status)).fg(Color::Yellow),
+            Span::raw("  "),
+            Span::raw(help).fg(Color::DarkGray),
+        ]);
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+
+    fn draw_results(&mut self, frame: &mut Frame, area: Rect) {
+        let items = self
+            .hits
+            .iter()
+            .map(|hit| {
+                let name = hit.path.file_name().unwrap_or_default().to_string_lossy();
+                let mut lines = vec![Line::from(vec![
+                    Span::raw(name.into_owned()).bold(),
+                    Span::raw(format!("  {:.5}", hit.score)).fg(Color::Blue),
+                ])];
+                lines.push(Line::from(hit.path.to_string_lossy().into_owned()).fg(Color::DarkGray));
+                if let Some(title) = &hit.title {
+                    lines.push(Line::from(title.clone()).italic());
+                }
+                for definition in &hit.definitions {
+                    lines.push(Line::from(vec![
+                        Span::raw(definition.kind.name()).fg(Color::Magenta),
+                        Span::raw(format!(" {} ", definition.name)).bold(),
+                        Span::raw(format!("(line {})", definition.line)).fg(Color::DarkGray),
+                    ]));
+                }
+                ListItem::new(lines)
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Results "))
+            .highlight_style(Style::new().bg(Color::DarkGray))
+            .highlight_symbol("▌");
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_preview(&mut self, frame: &mut Frame, area: Rect) {
+        self.preview_height = area.height.saturating_sub(2);
+        let Some(hit) = self.selected() else {
+            frame.render_widget(Block::bordered().title(" Preview "), area);
+            return;
+        };
+        let title = format!(" {} ", hit.path.display());
+        let block = Block::bordered().title(title);
+        let paragraph = match self.previews.get(&hit.path) {
+            Some(Preview { text: Ok(text), code }) => {
+                let matches = self.matches(&hit.path);
+                Paragraph::new(preview_lines(text, &matches, *code)).scroll((self.scroll, 0))
+            }
+            Some(Preview { text: Err(e), .. }) => Paragraph::new(e.as_str()).fg(Color::Red),
+            None => Paragraph::new(""),
+        };
+        frame.render_widget(paragraph.block(block), area);
+    }
+
+    /// Handles a key press, returning `false` to quit.
+    fn key(&mut self, key: KeyEvent, terminal: &mut DefaultTerminal) -> io::Result<bool> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(false),
+            KeyCode::Char('c') if control => return Ok(false),
+            KeyCode::Char('u') if control => {
+                self.input.clear();
+                self.search();
+            }
+            KeyCode::Char('n') if control => self.select(1),
+            KeyCode::Char('p') if control => self.select(-1),
+            KeyCode::Char(c) if !control => {
+                self.input.push(c);
+                self.search();
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.search();
+            }
+            KeyCode::Down => self.select(1),
+            KeyCode::Up => self.select(-1),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(self.preview_height / 2),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(self.preview_height / 2),
+            KeyCode::Enter => {
+                if let Some(hit) = self.selected() {
+                    let (path, line) = (hit.path.clone(), self.editor_line(hit));
+                    // An editor that cannot be started is shown in the status line, the search goes on.
+                    if let Err(message) = open_in_editor(terminal, &path, line)? {
+                        self.status = message;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+}
+
+/// The lines of a preview with the matches highlighted, numbered for code files.
+fn preview_lines<'t>(text: &'t str, matches: &[(u32, u32)], numbered: bool) -> Vec<Line<'t>> {
+    let highlight = Style::new().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let width = (text.matches('\n').count() + 1).to_string().len();
+    let mut lines = Vec::new();
+    let mut next = 0;
+    let mut offset = 0;
+    for (number, line) in text.split('\n').enumerate() {
+        let (start, end) = (offset, offset + line.len());
+        offset = end + 1;
+
+        let mut spans = Vec::new();
+        if numbered {
+            spans.push(Span::raw(format!("{:>width$} ", number + 1)).fg(Color::DarkGray));
+        }
+        // Matches are sorted, the ones ending before this line are done.
+        while matches.get(next).is_some_and(|&(_, match_end)| match_end as usize <= start) {
+            next += 1;
+        }
+        let mut at = start;
+        for &(match_start, match_end) in matches[next..].iter().take_while(|(match_start, _)| (*match_start as usize) < end) {
+            let (match_start, match_end) = ((match_start as usize).max(start), (match_end as usize).min(end));
+            let (Some(before), Some(matched)) = (text.get(at..match_start), text.get(match_start..match_end)) else {
+                continue;
+            };
+            spans.push(Span::raw(before.replace('\t', "    ")));
+            spans.push(Span::styled(matched.replace('\t', "    "), highlight));
+            at = match_end;
+        }
+        if let Some(rest) = text.get(at..end) {
+            spans.push(Span::raw(rest.trim_end_matches('\r').replace('\t', "    ")));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Leaves the terminal to the editor from `$VISUAL` or `$EDITOR` until it exits. Fails only when
+/// the terminal cannot be taken back, the inner error says why the editor could not be run.
+fn open_in_editor(
+    terminal: &mut DefaultTerminal,
+    path: &Path,
+    line: Option<usize>,
+) -> io::Result<Result<(), String>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let mut command = Command::new(program);
+    command.args(words);
+
+    // Most editors take `+line path`, some want `path:line`.
+    let name = Path::new(program).file_name().unwrap_or_default().to_string_lossy();
+    match (line, name.as_ref()) {
+        (Some(line), "code" | "codium") => command.arg("-g").arg(format!("{}:{line}", path.display())),
+        (Some(line), "hx" | "helix" | "subl" | "zed") => command.arg(format!("{}:{line}", path.display())),
+        (Some(line), _) => command.arg(format!("+{line}")).arg(path),
+        (None, _) => command.arg(path),
+    };
+
+    ratatui::try_restore()?;
+    let status = command.status();
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    terminal.clear()?;
+    Ok(status.map(|_| ()).map_err(|e| format!("could not run {program}: {e}")))
+}
+
+/// Loads the index and searches it as you type, with a preview of the selected document.
+pub fn run(collection: &CollectionConfig, registry: &ParserRegistry) -> Result<(), Error> {
+    let searcher = Searcher::load(index::get_indeces_path())?
+        .with_boosts(collection.boosts.clone())
+        .with_analysis(collection.analysis.clone());
+    let mut app = App {
+        searcher,
+        registry,
+        input: String::new(),
+        query: None,
+        hits: Vec::new(),
+        total: 0,
+        status: String::new(),
+        list: ListState::default(),
+        scroll: 0,
+        preview_height: 0,
+        previews: HashMap::new(),
+    };
+    app.status = format!("{} documents indexed", app.searcher.index().len());
+
+    let mut terminal = ratatui::try_init()?;
+    let result = (|| -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| app.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !app.key(key, &mut terminal)? {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    ratatui::try_restore()?;
+    Ok(result?)
+}
@@ -0,0 +1,130 @@
+"use strict";
+
+const PAGE_SIZE = 20;
+
+const queryInput = document.getElementById("query");
+const extSelect = document.getElementById("ext");
+const pathInput = document.getElementById("path");
+const statusLine = document.getElementById("status");
+const hitList = document.getElementById("hits");
+const moreButton = document.getElementById("more");
+const preview = document.getElementById("preview");
+
+let offset = 0;
+// Answers to outdated requests are dropped.
+let generation = 0;
+
+function element(tag, className, text) {
+  const node = document.createElement(tag);
+  if (className) node.className = className;
+  if (text !== undefined) node.textContent = text;
+  return node;
+}
+
+// Segments are `{text, match}` pieces of text, the matches get highlighted.
+function renderSegments(parent, segments) {
+  for (const segment of segments) {
+    parent.appendChild(segment.match ? element("mark", "", segment.text) : document.createTextNode(segment.text));
+  }
+}
+
+function searchUrl() {
+  const params = new URLSearchParams({ q: queryInput.value, limit: PAGE_SIZE, offset });
+  if (extSelect.value) params.set("ext", extSelect.value);
+  if (pathInput.value.trim()) params.set("path", pathInput.value.trim());
+  return "/api/search?" + params;
+}
+
+async function search(append) {
+  const current = ++generation;
+  if (!append) offset = 0;
+  if (!queryInput.value.trim()) {
+    hitList.replaceChildren();
+    statusLine.textContent = "";
+    moreButton.hidden = true;
+    return;
+  }
+
+  const response = await fetch(searchUrl());
+  const body = await response.json();
+  if (current !== generation) return;
+  if (!response.ok) {
+    statusLine.textContent = body.error;
+    statusLine.className = "error";
+    return;
+  }
+
+  statusLine.className = "muted";
+  statusLine.textContent = `${body.total} matching document${body.total === 1 ? "" : "s"}`;
+  if (!append) hitList.replaceChildren();
+  for (const result of body.results) {
+    hitList.appendChild(renderHit(result));
+  }
+  offset = body.offset + body.results.length;
+  moreButton.hidden = offset >= body.total;
+}
+
+function renderHit(result) {
+  const item = element("li");
+  const name = result.path.split("/").pop();
+  item.appendChild(element("div", "title", result.title || name));
+  item.appendChild(element("div", "path", result.path));
+  item.appendChild(element("div", "score", `Score: ${result.score.toFixed(5)}`));
+  for (const definition of result.definitions) {
+    item.appendChild(element("div", "definition", `${definition.kind} ${definition.name} (line ${definition.line})`));
+  }
+  if (result.snippet.length) {
+    const snippet = element("div", "snippet");
+    renderSegments(snippet, result.snippet);
+    item.appendChild(snippet);
+  }
+  item.addEventListener("click", () => {
+    for (const selected of hitList.querySelectorAll(".selected")) selected.classList.remove("selected");
+    item.classList.add("selected");
+    showDocument(result.id);
+  });
+  return item;
+}
+
+async function showDocument(id) {
+  const params = new URLSearchParams({ id, q: queryInput.value });
+  const response = await fetch("/api/doc?" + params);
+  const doc = await response.json();
+  preview.replaceChildren();
+  preview.appendChild(element("h2", "", doc.title || doc.path || id));
+  preview.appendChild(element("div", "path", doc.path || id));
+  if (!response.ok || doc.error) {
+    preview.appendChild(element("p", "error", doc.error));
+    return;
+  }
+  const text = element("pre");
+  renderSegments(text, doc.segments || [{ text: doc.text, match: false }]);
+  preview.appendChild(text);
+  const first = text.querySelector("mark");
+  if (first) first.scrollIntoView({ block: "center" });
+}
+
+async function loadExtensions() {
+  const response = await fetch("/api/stats");
+  const stats = await response.json();
+  const extensions = Object.entries(stats.extensions)
+    .filter(([ext]) => ext)
+    .sort((a, b) => b[1] - a[1]);
+  for (const [ext, count] of extensions) {
+    const option = element("option", "", `.${ext} (${count})`);
+    option.value = ext;
+    extSelect.appendChild(option);
+  }
+}
+
+let debounce;
+function scheduleSearch() {
+  clearTimeout(debounce);
+  debounce = setTimeout(() => search(false), 150);
+}
+
+queryInput.addEventListener("input", scheduleSearch);
+pathInput.addEventListener("input", scheduleSearch);
+extSelect.addEventListener("change", () => search(false));
+moreButton.addEventListener("click", () => search(true));
+loadExtensions();